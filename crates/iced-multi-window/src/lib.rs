@@ -12,23 +12,94 @@
 //!
 //! Next, add a `WindowManager` to your app's state. It keeps track of all of the `Id`s and corresponding `Window`s that are currently open. It also provides `view`, `theme`, and `title` methods that return the proper output for the specified `Id`.
 //!
-//! You have to manually inform the `WindowManager` when a window is closed. This can be done by subscribing to `iced::window::close_events()` and passing the `Id` of each closed window to `WindowManager::was_closed()`.
+//! Windows that need to hold mutable data (like the contents of a form) can return an initial value from `Window::state`. The `WindowManager` stores it alongside the window and hands it to `Window::view`, along with the window's `Id`. Messages can be routed back to a specific window with `WindowManager::update`, which calls that window's `Window::update` with mutable access to its state.
+//!
+//! Windows that should only ever have one instance open (e.g. a settings window) can return `true` from `Window::singleton`. Opening a singleton window that's already open focuses the existing instance instead, so there's no need to check for it at each call site.
+//!
+//! A window can declare a parent with `Window::parent`. When a window is opened, it is attached to an open instance of its parent. Children are closed along with their parent, and can be placed relative to it with `Window::offset`. A child that is `Window::modal` blocks mouse input to its parent until it is closed. Keyboard input handled in subscriptions doesn't go through the parent's view, so check `WindowManager::is_blocked` before acting on it.
+//!
+//! You have to manually inform the `WindowManager` when a window is closed. This can be done by subscribing to `iced::window::close_events()` and passing the `Id` of each closed window to `WindowManager::was_closed()`. The returned task closes the window's children, and should be run. Similarly, pass window movements (`iced::window::Event::Opened` and `iced::window::Event::Moved`) to `WindowManager::was_moved()` so that children can be positioned relative to their parents.
 
 use dyn_clone::DynClone;
 use iced::{
-    window::{self, Id},
-    Element, Task,
+    widget::{opaque, stack, Space},
+    window::{self, Id, Position},
+    Element, Length, Point, Task, Vector,
 };
-use std::{any::type_name, collections::HashMap};
+use std::{
+    any::{type_name, Any},
+    collections::HashMap,
+};
+
+/// Mutable state owned by a single open window, along with the window's `Id`.
+pub struct State {
+    id: Id,
+    data: Option<Box<dyn Any>>,
+}
+
+impl State {
+    /// The `Id` of the window this state belongs to. Use this to address messages to the window.
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    /// Returns the window's state, panicking if it has none or it isn't of type `T`.
+    pub fn get<T: 'static>(&self) -> &T {
+        self.data
+            .as_ref()
+            .and_then(|data| data.downcast_ref())
+            .expect("Window state is missing or of the wrong type")
+    }
+
+    /// Returns the window's state mutably, panicking if it has none or it isn't of type `T`.
+    pub fn get_mut<T: 'static>(&mut self) -> &mut T {
+        self.data
+            .as_mut()
+            .and_then(|data| data.downcast_mut())
+            .expect("Window state is missing or of the wrong type")
+    }
+}
 
 #[allow(private_bounds)]
 pub trait Window<App, Theme, Message, Renderer = iced::Renderer>:
     Send + std::fmt::Debug + DynClone
 {
-    fn view<'a>(&'a self, app: &'a App) -> iced::Element<'a, Message, Theme, Renderer>;
+    fn view<'a>(
+        &'a self,
+        app: &'a App,
+        state: &'a State,
+    ) -> iced::Element<'a, Message, Theme, Renderer>;
     fn title(&self, app: &App) -> String;
     fn theme(&self, app: &App) -> Theme;
     fn settings(&self) -> window::Settings;
+    /// The initial mutable state of this window, created when it is opened. Stateless windows
+    /// don't need to implement this.
+    fn state(&self) -> Option<Box<dyn Any>> {
+        None
+    }
+    /// Handles a message routed to this window by `WindowManager::update`.
+    fn update(&self, _state: &mut State, _message: Message) -> Task<Message> {
+        Task::none()
+    }
+    /// The window this window belongs to. When opened, the window is attached to an open instance
+    /// of its parent, and is closed along with it.
+    fn parent(&self) -> Option<Box<dyn Window<App, Theme, Message, Renderer>>> {
+        None
+    }
+    /// Where to place this window relative to the top-left corner of its parent. Ignored if the
+    /// parent's position is unknown or the window's settings specify a position.
+    fn offset(&self) -> Option<Vector> {
+        None
+    }
+    /// Whether this window blocks input to its parent while it's open.
+    fn modal(&self) -> bool {
+        false
+    }
+    /// Whether only one instance of this window may be open at a time. Opening a singleton window
+    /// that's already open focuses the existing instance instead.
+    fn singleton(&self) -> bool {
+        false
+    }
     /// The unique identifier for this window. This includes any internal data.
     fn id(&self) -> String {
         let data = format!("{self:?}");
@@ -59,8 +130,12 @@ impl<App, Theme, Message, Renderer, T: Window<App, Theme, Message, Renderer>> Pa
 impl<App, Theme, Message, Renderer> Window<App, Theme, Message, Renderer>
     for Box<dyn Window<App, Theme, Message, Renderer>>
 {
-    fn view<'a>(&'a self, app: &'a App) -> iced::Element<'a, Message, Theme, Renderer> {
-        self.as_ref().view(app)
+    fn view<'a>(
+        &'a self,
+        app: &'a App,
+        state: &'a State,
+    ) -> iced::Element<'a, Message, Theme, Renderer> {
+        self.as_ref().view(app, state)
     }
 
     fn title(&self, app: &App) -> String {
@@ -75,6 +150,30 @@ impl<App, Theme, Message, Renderer> Window<App, Theme, Message, Renderer>
         self.as_ref().settings()
     }
 
+    fn state(&self) -> Option<Box<dyn Any>> {
+        self.as_ref().state()
+    }
+
+    fn update(&self, state: &mut State, message: Message) -> Task<Message> {
+        self.as_ref().update(state, message)
+    }
+
+    fn parent(&self) -> Option<Box<dyn Window<App, Theme, Message, Renderer>>> {
+        self.as_ref().parent()
+    }
+
+    fn offset(&self) -> Option<Vector> {
+        self.as_ref().offset()
+    }
+
+    fn modal(&self) -> bool {
+        self.as_ref().modal()
+    }
+
+    fn singleton(&self) -> bool {
+        self.as_ref().singleton()
+    }
+
     fn id(&self) -> String {
         self.as_ref().id()
     }
@@ -86,6 +185,10 @@ impl<App, Theme, Message, Renderer> Window<App, Theme, Message, Renderer>
 
 pub struct WindowManager<App, Theme, Message, Renderer = iced::Renderer> {
     windows: HashMap<Id, Box<dyn Window<App, Theme, Message, Renderer>>>,
+    states: HashMap<Id, State>,
+    /// `{[child]: [parent]}`
+    parents: HashMap<Id, Id>,
+    positions: HashMap<Id, Point>,
 }
 
 impl<App, Theme, Message, Renderer> WindowManager<App, Theme, Message, Renderer> {
@@ -97,8 +200,42 @@ impl<App, Theme, Message, Renderer> WindowManager<App, Theme, Message, Renderer>
             .as_ref()
     }
 
-    pub fn view<'a>(&'a self, app: &'a App, id: Id) -> Element<'a, Message, Theme, Renderer> {
-        self.get(id).view(app)
+    pub fn view<'a>(&'a self, app: &'a App, id: Id) -> Element<'a, Message, Theme, Renderer>
+    where
+        Message: 'a,
+        Theme: 'a,
+        Renderer: iced::advanced::Renderer + 'a,
+    {
+        let view = self.get(id).view(app, &self.states[&id]);
+        if self.is_blocked(id) {
+            // Swallows all mouse input until the modal child is closed
+            stack![
+                view,
+                opaque(Space::new().width(Length::Fill).height(Length::Fill))
+            ]
+            .into()
+        } else {
+            view
+        }
+    }
+
+    /// Routes a message to the window with the given Id. Does nothing if the window has since been
+    /// closed.
+    pub fn update(&mut self, id: Id, message: Message) -> Task<Message> {
+        match (self.windows.get(&id), self.states.get_mut(&id)) {
+            (Some(window), Some(state)) => window.update(state, message),
+            _ => Task::none(),
+        }
+    }
+
+    /// Returns the state of the window with the given Id, if it is open.
+    pub fn state(&self, id: Id) -> Option<&State> {
+        self.states.get(&id)
+    }
+
+    /// Returns the state of the window with the given Id mutably, if it is open.
+    pub fn state_mut(&mut self, id: Id) -> Option<&mut State> {
+        self.states.get_mut(&id)
     }
 
     pub fn title(&self, app: &App, id: Id) -> String {
@@ -109,11 +246,71 @@ impl<App, Theme, Message, Renderer> WindowManager<App, Theme, Message, Renderer>
         self.get(id).theme(app)
     }
 
+    /// Opens the given window. If the window is a singleton and is already open, the existing
+    /// instance is brought to the front instead, and its `Id` is returned. In that case, the
+    /// returned task doesn't produce any output.
     pub fn open(
         &mut self,
         window: Box<dyn Window<App, Theme, Message, Renderer>>,
     ) -> (Id, Task<Id>) {
-        let (id, task) = window::open(window.settings());
+        self.open_with_position(window, None)
+    }
+
+    /// Opens the given window at the given position, regardless of its settings and
+    /// `Window::offset`. Useful for restoring windows from a previous session.
+    pub fn open_at(
+        &mut self,
+        window: Box<dyn Window<App, Theme, Message, Renderer>>,
+        position: Point,
+    ) -> (Id, Task<Id>) {
+        self.open_with_position(window, Some(position))
+    }
+
+    fn open_with_position(
+        &mut self,
+        window: Box<dyn Window<App, Theme, Message, Renderer>>,
+        position: Option<Point>,
+    ) -> (Id, Task<Id>) {
+        if window.singleton() {
+            if let Some((id, _)) = self.instances_of(&window).first() {
+                let id = **id;
+                return (
+                    id,
+                    Task::batch([window::minimize(id, false), window::gain_focus(id)]),
+                );
+            }
+        }
+
+        let parent = window.parent().and_then(|parent| {
+            self.windows
+                .iter()
+                .find(|(_, w)| **w == parent)
+                .map(|(id, _)| *id)
+        });
+
+        let mut settings = window.settings();
+        if let (Position::Default, Some(offset), Some(parent_position)) = (
+            settings.position,
+            window.offset(),
+            parent.and_then(|parent| self.positions.get(&parent)),
+        ) {
+            settings.position = Position::Specific(*parent_position + offset);
+        }
+        if let Some(position) = position {
+            settings.position = Position::Specific(position);
+        }
+
+        let (id, task) = window::open(settings);
+        if let Some(parent) = parent {
+            self.parents.insert(id, parent);
+        }
+        self.states.insert(
+            id,
+            State {
+                id,
+                data: window.state(),
+            },
+        );
         self.windows.insert(id, window);
         (id, task)
     }
@@ -140,14 +337,53 @@ impl<App, Theme, Message, Renderer> WindowManager<App, Theme, Message, Renderer>
         Task::batch(tasks)
     }
 
-    /// Checks for any open instances of the given window.
-    pub fn any_of(&self, window: &impl Window<App, Theme, Message, Renderer>) -> bool {
-        self.windows.values().any(|w| w == window)
+    /// Updates internal state to reflect that the given window Id  was closed. The returned task
+    /// closes the window's children.
+    pub fn was_closed(&mut self, id: Id) -> Task<Id> {
+        self.windows.remove(&id);
+        self.states.remove(&id);
+        self.parents.remove(&id);
+        self.positions.remove(&id);
+
+        Task::batch(self.children_of(id).into_iter().map(window::close))
     }
 
-    /// Updates internal state to reflect that the given window Id  was closed.
-    pub fn was_closed(&mut self, id: Id) {
-        self.windows.remove(&id);
+    /// Returns the last known position of the given window.
+    pub fn position(&self, id: Id) -> Option<Point> {
+        self.positions.get(&id).copied()
+    }
+
+    /// Returns an iterator over all open windows and their Ids.
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (Id, &Box<dyn Window<App, Theme, Message, Renderer>>)> {
+        self.windows.iter().map(|(id, window)| (*id, window))
+    }
+
+    /// Updates internal state to reflect that the given window Id was moved.
+    pub fn was_moved(&mut self, id: Id, position: Point) {
+        self.positions.insert(id, position);
+    }
+
+    /// Returns the Id of the given window's parent, if it has one.
+    pub fn parent_of(&self, id: Id) -> Option<Id> {
+        self.parents.get(&id).copied()
+    }
+
+    /// Returns the Ids of all open children of the given window.
+    pub fn children_of(&self, id: Id) -> Vec<Id> {
+        self.parents
+            .iter()
+            .filter(|(_, parent)| **parent == id)
+            .map(|(child, _)| *child)
+            .collect()
+    }
+
+    /// Checks whether the given window has an open modal child.
+    pub fn is_blocked(&self, id: Id) -> bool {
+        self.children_of(id)
+            .into_iter()
+            .any(|child| self.windows.get(&child).is_some_and(|w| w.modal()))
     }
 
     /// Returns all instances of the given window and their associated Ids.
@@ -168,6 +404,9 @@ impl<App, Theme, Message, Renderer> Default for WindowManager<App, Theme, Messag
     fn default() -> Self {
         Self {
             windows: HashMap::new(),
+            states: HashMap::new(),
+            parents: HashMap::new(),
+            positions: HashMap::new(),
         }
     }
 }
@@ -0,0 +1,15 @@
+use crate::{layout::BoardElement, style::ElementStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Elements copied from the graphical editor. This is written to the clipboard as JSON, so that
+/// elements can be pasted into other layouts or edited by hand.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct ClipboardElements {
+    pub elements: Vec<BoardElement>,
+    /// Styles of the copied elements, by the elements' ids. Elements that use the default style
+    /// have no entry.
+    #[serde(default)]
+    pub element_styles: HashMap<u32, ElementStyle>,
+}
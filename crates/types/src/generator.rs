@@ -0,0 +1,670 @@
+//! Generates standard layouts from a few parameters, so that new keyboards don't have to be drawn
+//! by hand.
+
+use crate::{
+    labels::{self, Locale},
+    layout::*,
+};
+use std::str::FromStr;
+
+/// Space left around the edges of the window, in pixels.
+const MARGIN: f32 = 9.0;
+
+/// The keycode NohBoard layouts use for numpad Enter, which has no virtual-key code of its own.
+pub const NUMPAD_ENTER: u32 = 0x401;
+
+/// Which keys to include.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FormFactor {
+    /// No keyboard keys. Combined with [`LayoutOptions::mouse`], this gives a mouse-only layout.
+    NoKeys,
+    Numpad,
+    /// The main block, with Escape in place of the grave key.
+    Sixty,
+    /// 60%, plus arrow keys and a column of navigation keys.
+    SixtyFive,
+    /// 65%, plus a function row.
+    SeventyFive,
+    /// Tenkeyless.
+    Tkl,
+    #[default]
+    Full,
+}
+
+impl FormFactor {
+    pub const ALL: [FormFactor; 7] = [
+        FormFactor::NoKeys,
+        FormFactor::Numpad,
+        FormFactor::Sixty,
+        FormFactor::SixtyFive,
+        FormFactor::SeventyFive,
+        FormFactor::Tkl,
+        FormFactor::Full,
+    ];
+}
+
+impl std::fmt::Display for FormFactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormFactor::NoKeys => write!(f, "None"),
+            FormFactor::Numpad => write!(f, "Numpad"),
+            FormFactor::Sixty => write!(f, "60%"),
+            FormFactor::SixtyFive => write!(f, "65%"),
+            FormFactor::SeventyFive => write!(f, "75%"),
+            FormFactor::Tkl => write!(f, "TKL"),
+            FormFactor::Full => write!(f, "Full"),
+        }
+    }
+}
+
+impl FromStr for FormFactor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().trim_end_matches('%') {
+            "none" => Ok(FormFactor::NoKeys),
+            "numpad" => Ok(FormFactor::Numpad),
+            "60" => Ok(FormFactor::Sixty),
+            "65" => Ok(FormFactor::SixtyFive),
+            "75" => Ok(FormFactor::SeventyFive),
+            "tkl" => Ok(FormFactor::Tkl),
+            "full" => Ok(FormFactor::Full),
+            _ => Err(format!(
+                "unknown form factor `{s}`, expected one of none, numpad, 60, 65, 75, tkl, full"
+            )),
+        }
+    }
+}
+
+/// The physical arrangement of the main block. Legends follow the US, UK and Japanese layouts
+/// respectively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Standard {
+    #[default]
+    Ansi,
+    Iso,
+    Jis,
+}
+
+impl Standard {
+    pub const ALL: [Standard; 3] = [Standard::Ansi, Standard::Iso, Standard::Jis];
+
+    fn locale(&self) -> Locale {
+        match self {
+            Standard::Ansi => Locale::Us,
+            Standard::Iso => Locale::Uk,
+            Standard::Jis => Locale::Jp,
+        }
+    }
+}
+
+impl std::fmt::Display for Standard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Standard::Ansi => write!(f, "ANSI"),
+            Standard::Iso => write!(f, "ISO"),
+            Standard::Jis => write!(f, "JIS"),
+        }
+    }
+}
+
+impl FromStr for Standard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ansi" => Ok(Standard::Ansi),
+            "iso" => Ok(Standard::Iso),
+            "jis" => Ok(Standard::Jis),
+            _ => Err(format!(
+                "unknown standard `{s}`, expected one of ansi, iso, jis"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutOptions {
+    pub form_factor: FormFactor,
+    pub standard: Standard,
+    /// Size of a 1u key in pixels, including the gap between keys.
+    pub unit: f32,
+    /// Space left between adjacent keys, in pixels.
+    pub gap: f32,
+    /// Whether to add mouse buttons, scroll directions and a speed indicator below the keys.
+    pub mouse: bool,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            form_factor: FormFactor::default(),
+            standard: Standard::default(),
+            unit: 44.0,
+            gap: 1.0,
+            mouse: false,
+        }
+    }
+}
+
+impl LayoutOptions {
+    /// Checks that keys generated with these options have a positive size.
+    pub fn check(&self) -> Result<(), String> {
+        if !self.unit.is_finite() || self.unit <= 0.0 {
+            return Err(format!(
+                "the key size must be positive, but is {}",
+                self.unit
+            ));
+        }
+        if !(0.0..self.unit).contains(&self.gap) {
+            return Err(format!(
+                "the gap must be at least 0 and smaller than the key size, but is {}",
+                self.gap
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Builds a layout, sized to fit its elements.
+pub fn generate(options: &LayoutOptions) -> Result<Layout, String> {
+    options.check()?;
+    let mut grid = Grid {
+        cells: Vec::new(),
+        standard: options.standard,
+    };
+    match options.form_factor {
+        FormFactor::NoKeys => {}
+        FormFactor::Numpad => grid.numpad(0.0, 0.0),
+        FormFactor::Sixty => grid.alphanumeric(0.0, true, false),
+        FormFactor::SixtyFive => {
+            grid.alphanumeric(0.0, true, true);
+            grid.navigation_column(0.0, &[0x2E, 0x21, 0x22, 0x23]);
+        }
+        FormFactor::SeventyFive => {
+            grid.compact_function_row();
+            grid.alphanumeric(1.25, false, true);
+            grid.navigation_column(1.25, &[0x24, 0x21, 0x22, 0x23]);
+        }
+        FormFactor::Tkl | FormFactor::Full => {
+            grid.function_row();
+            grid.alphanumeric(1.5, false, false);
+            grid.navigation(15.25, 1.5);
+            if options.form_factor == FormFactor::Full {
+                grid.numpad(18.5, 1.5);
+            }
+        }
+    }
+    if options.mouse {
+        let height = grid.size().height;
+        grid.mouse(if height > 0.0 { height + 0.5 } else { 0.0 });
+    }
+
+    if grid.cells.is_empty() {
+        return Ok(Layout {
+            version: Some(2),
+            width: 400.0,
+            height: 200.0,
+            elements: Vec::new(),
+        });
+    }
+    Ok(grid.into_layout(options.unit, options.gap))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Key,
+    MouseKey,
+    MouseScroll,
+    MouseSpeedIndicator,
+}
+
+/// An element placed on a grid of 1u cells.
+struct Cell {
+    kind: Kind,
+    keycode: u32,
+    /// Legend, if it isn't the one from the label table.
+    legend: Option<&'static str>,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    /// Width of the notch cut from the bottom left corner, as on ISO Enter.
+    notch: f32,
+}
+
+struct Grid {
+    cells: Vec<Cell>,
+    standard: Standard,
+}
+
+impl Grid {
+    fn key(&mut self, keycode: u32, x: f32, y: f32, width: f32, height: f32) -> &mut Cell {
+        self.cells.push(Cell {
+            kind: Kind::Key,
+            keycode,
+            legend: None,
+            x,
+            y,
+            width,
+            height,
+            notch: 0.0,
+        });
+        self.cells.last_mut().unwrap()
+    }
+
+    /// Places keys of the given `(keycode, width)` left to right, starting at `x`.
+    fn row(&mut self, x: f32, y: f32, keys: &[(u32, f32)]) {
+        let mut x = x;
+        for &(keycode, width) in keys {
+            self.key(keycode, x, y, width, 1.0);
+            x += width;
+        }
+    }
+
+    /// Size of the grid in units.
+    fn size(&self) -> iced::Size {
+        iced::Size {
+            width: self
+                .cells
+                .iter()
+                .map(|cell| cell.x + cell.width)
+                .fold(0.0, f32::max),
+            height: self
+                .cells
+                .iter()
+                .map(|cell| cell.y + cell.height)
+                .fold(0.0, f32::max),
+        }
+    }
+
+    fn function_row(&mut self) {
+        self.key(0x1B, 0.0, 0.0, 1.0, 1.0);
+        self.function_keys(&[2.0, 6.5, 11.0]);
+    }
+
+    /// A function row without gaps between Escape and the navigation keys, as on 75% keyboards.
+    fn compact_function_row(&mut self) {
+        self.key(0x1B, 0.0, 0.0, 1.0, 1.0);
+        self.function_keys(&[1.25, 5.5, 9.75]);
+        self.row(14.0, 0.0, &[(0x2C, 1.0), (0x2E, 1.0)]);
+    }
+
+    /// F1 to F12, in groups of four starting at each of `groups`.
+    fn function_keys(&mut self, groups: &[f32; 3]) {
+        for (i, x) in groups.iter().enumerate() {
+            for j in 0..4 {
+                self.key(0x70 + (i * 4 + j) as u32, x + j as f32, 0.0, 1.0, 1.0);
+            }
+        }
+    }
+
+    /// The main block of a keyboard, from the number row to the bottom row. `escape` replaces the
+    /// key left of 1 with Escape. `compact` makes room for arrow keys in the bottom right corner.
+    fn alphanumeric(&mut self, y: f32, escape: bool, compact: bool) {
+        let jis = self.standard == Standard::Jis;
+        let iso = self.standard == Standard::Iso;
+        let letters =
+            |keys: &str| -> Vec<(u32, f32)> { keys.bytes().map(|key| (key as u32, 1.0)).collect() };
+
+        let corner = if escape { 0x1B } else { 0xC0 };
+        let mut number_row = vec![(corner, 1.0)];
+        number_row.extend((0x31..=0x39).map(|code| (code, 1.0)));
+        number_row.extend([(0x30, 1.0), (0xBD, 1.0)]);
+        if jis {
+            number_row.extend([(0xBB, 1.0), (0xDF, 1.0), (0x08, 1.0)]);
+        } else {
+            number_row.extend([(0xBB, 1.0), (0x08, 2.0)]);
+        }
+
+        let mut top = vec![(0x09, 1.5)];
+        top.extend(letters("QWERTYUIOP"));
+        let mut home = vec![(0x14, 1.75)];
+        home.extend(letters("ASDFGHJKL"));
+        let mut bottom = match iso {
+            true => vec![(0xA0, 1.25), (0xE2, 1.0)],
+            false => vec![(0xA0, 2.25)],
+        };
+        bottom.extend(letters("ZXCVBNM"));
+        bottom.extend([(0xBC, 1.0), (0xBE, 1.0), (0xBF, 1.0)]);
+        // Compact layouts make room for the up arrow by shrinking right Shift
+        let right_shift = if compact { 1.75 } else { 2.75 };
+
+        match self.standard {
+            Standard::Ansi => {
+                top.extend([(0xDB, 1.0), (0xDD, 1.0), (0xDC, 1.5)]);
+                home.extend([(0xBA, 1.0), (0xDE, 1.0), (0x0D, 2.25)]);
+                bottom.push((0xA1, right_shift));
+            }
+            Standard::Iso => {
+                top.extend([(0xDB, 1.0), (0xDD, 1.0)]);
+                home.extend([(0xBA, 1.0), (0xDE, 1.0), (0xDC, 1.0)]);
+                bottom.push((0xA1, right_shift));
+                self.key(0x0D, 13.5, y + 1.0, 1.5, 2.0).notch = 0.25;
+            }
+            Standard::Jis => {
+                top.extend([(0xDB, 1.0), (0xDD, 1.0)]);
+                home.extend([(0xBA, 1.0), (0xDE, 1.0), (0xDC, 1.0)]);
+                bottom.extend([(0xC1, 1.0), (0xA1, right_shift - 1.0)]);
+                self.key(0x0D, 13.5, y + 1.0, 1.5, 2.0).notch = 0.25;
+            }
+        }
+
+        let bottom_row: &[(u32, f32)] = match (jis, compact) {
+            (false, false) => &[
+                (0xA2, 1.25),
+                (0x5B, 1.25),
+                (0xA4, 1.25),
+                (0x20, 6.25),
+                (0xA5, 1.25),
+                (0x5C, 1.25),
+                (0x5D, 1.25),
+                (0xA3, 1.25),
+            ],
+            (false, true) => &[
+                (0xA2, 1.25),
+                (0x5B, 1.25),
+                (0xA4, 1.25),
+                (0x20, 6.25),
+                (0xA5, 1.0),
+                (0x5D, 1.0),
+                (0xA3, 1.0),
+            ],
+            (true, false) => &[
+                (0xA2, 1.25),
+                (0x5B, 1.25),
+                (0xA4, 1.25),
+                (0x1D, 1.25),
+                (0x20, 3.5),
+                (0x1C, 1.25),
+                (0x15, 1.25),
+                (0xA5, 1.25),
+                (0x5D, 1.25),
+                (0xA3, 1.5),
+            ],
+            (true, true) => &[
+                (0xA2, 1.25),
+                (0x5B, 1.25),
+                (0xA4, 1.25),
+                (0x1D, 1.25),
+                (0x20, 4.0),
+                (0x1C, 1.0),
+                (0x15, 1.0),
+                (0xA5, 1.0),
+                (0xA3, 1.0),
+            ],
+        };
+
+        self.row(0.0, y, &number_row);
+        self.row(0.0, y + 1.0, &top);
+        self.row(0.0, y + 2.0, &home);
+        self.row(0.0, y + 3.0, &bottom);
+        self.row(0.0, y + 4.0, bottom_row);
+        if compact {
+            self.key(0x26, 14.0, y + 3.0, 1.0, 1.0);
+            self.row(13.0, y + 4.0, &[(0x25, 1.0), (0x28, 1.0), (0x27, 1.0)]);
+        }
+    }
+
+    /// Print Screen, Scroll Lock and Pause on the function row, and the editing and arrow keys
+    /// next to the main block at `y`.
+    fn navigation(&mut self, x: f32, y: f32) {
+        self.row(x, 0.0, &[(0x2C, 1.0), (0x91, 1.0), (0x13, 1.0)]);
+        self.row(x, y, &[(0x2D, 1.0), (0x24, 1.0), (0x21, 1.0)]);
+        self.row(x, y + 1.0, &[(0x2E, 1.0), (0x23, 1.0), (0x22, 1.0)]);
+        self.key(0x26, x + 1.0, y + 3.0, 1.0, 1.0);
+        self.row(x, y + 4.0, &[(0x25, 1.0), (0x28, 1.0), (0x27, 1.0)]);
+    }
+
+    /// The column of navigation keys right of a compact main block at `y`, from the top down.
+    fn navigation_column(&mut self, y: f32, keycodes: &[u32]) {
+        for (i, keycode) in keycodes.iter().enumerate() {
+            self.key(*keycode, 15.0, y + i as f32, 1.0, 1.0);
+        }
+    }
+
+    fn numpad(&mut self, x: f32, y: f32) {
+        self.row(x, y, &[(0x90, 1.0), (0x6F, 1.0), (0x6A, 1.0), (0x6D, 1.0)]);
+        self.row(x, y + 1.0, &[(0x67, 1.0), (0x68, 1.0), (0x69, 1.0)]);
+        self.key(0x6B, x + 3.0, y + 1.0, 1.0, 2.0);
+        self.row(x, y + 2.0, &[(0x64, 1.0), (0x65, 1.0), (0x66, 1.0)]);
+        self.row(x, y + 3.0, &[(0x61, 1.0), (0x62, 1.0), (0x63, 1.0)]);
+        self.key(NUMPAD_ENTER, x + 3.0, y + 3.0, 1.0, 2.0).legend = Some("Enter");
+        self.row(x, y + 4.0, &[(0x60, 2.0), (0x6E, 1.0)]);
+    }
+
+    fn mouse(&mut self, y: f32) {
+        let mut mouse = |kind, keycode, legend, x, dy| {
+            let cell = self.key(keycode, x, y + dy, 1.0, 1.0);
+            cell.kind = kind;
+            cell.legend = Some(legend);
+        };
+        mouse(Kind::MouseKey, 4, "X2", 0.0, 0.0);
+        mouse(Kind::MouseKey, 3, "X1", 0.0, 1.0);
+        mouse(Kind::MouseKey, 0, "LMB", 1.0, 1.0);
+        mouse(Kind::MouseKey, 2, "MMB", 2.0, 1.0);
+        mouse(Kind::MouseKey, 1, "RMB", 3.0, 1.0);
+        mouse(Kind::MouseScroll, 0, "SCRU", 5.0, 0.0);
+        mouse(Kind::MouseScroll, 3, "SCRL", 4.0, 1.0);
+        mouse(Kind::MouseScroll, 1, "SCRD", 5.0, 1.0);
+        mouse(Kind::MouseScroll, 2, "SCRR", 6.0, 1.0);
+        mouse(Kind::MouseSpeedIndicator, 0, "", 2.0, 0.0);
+    }
+
+    fn into_layout(self, unit: f32, gap: f32) -> Layout {
+        let size = self.size();
+        let locale = self.standard.locale();
+        Layout {
+            version: Some(2),
+            width: size.width * unit + 2.0 * MARGIN - gap,
+            height: size.height * unit + 2.0 * MARGIN - gap,
+            elements: self
+                .cells
+                .into_iter()
+                .enumerate()
+                .map(|(id, cell)| cell.into_element(id as u32, unit, gap, locale))
+                .collect(),
+        }
+    }
+}
+
+impl Cell {
+    fn into_element(self, id: u32, unit: f32, gap: f32, locale: Locale) -> BoardElement {
+        let left = MARGIN + self.x * unit;
+        let top = MARGIN + self.y * unit;
+        let right = left + self.width * unit - gap;
+        let bottom = top + self.height * unit - gap;
+        let text_position = SerializablePoint {
+            x: ((left + right) / 2.0).into(),
+            y: ((top + bottom) / 2.0).into(),
+        };
+
+        if self.kind == Kind::MouseSpeedIndicator {
+            return BoardElement::MouseSpeedIndicator(MouseSpeedIndicatorDefinition {
+                id,
+                location: text_position,
+                radius: (unit - gap) / 2.0,
+            });
+        }
+
+        let point = |x: f32, y: f32| SerializablePoint {
+            x: x.into(),
+            y: y.into(),
+        };
+        let boundaries = if self.notch > 0.0 {
+            let notch = left + self.notch * unit;
+            let middle = top + unit - gap;
+            vec![
+                point(left, top),
+                point(right, top),
+                point(right, bottom),
+                point(notch, bottom),
+                point(notch, middle),
+                point(left, middle),
+            ]
+        } else {
+            vec![
+                point(left, top),
+                point(right, top),
+                point(right, bottom),
+                point(left, bottom),
+            ]
+        };
+
+        let (text, shift_text) = match self.legend {
+            Some(legend) => (legend.to_owned(), legend.to_owned()),
+            None => labels::label(self.keycode, locale).unwrap_or_default(),
+        };
+        let key = KeyboardKeyDefinition {
+            id,
+            boundaries,
+            text_position,
+            key_codes: vec![self.keycode],
+            change_on_caps: shift_text != text && text.chars().all(char::is_alphabetic),
+            text,
+            shift_text,
+            corner_radius: 0.0,
+            shape: KeyShape::Polygon,
+        };
+        match self.kind {
+            Kind::Key => BoardElement::KeyboardKey(key),
+            Kind::MouseKey => BoardElement::MouseKey(key.into()),
+            Kind::MouseScroll => BoardElement::MouseScroll(key.into()),
+            Kind::MouseSpeedIndicator => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn all_options() -> impl Iterator<Item = LayoutOptions> {
+        FormFactor::ALL.into_iter().flat_map(|form_factor| {
+            Standard::ALL.into_iter().flat_map(move |standard| {
+                [false, true].map(|mouse| LayoutOptions {
+                    form_factor,
+                    standard,
+                    mouse,
+                    ..LayoutOptions::default()
+                })
+            })
+        })
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        for options in all_options() {
+            let layout = generate(&options).unwrap();
+            let mut ids = HashSet::new();
+            for element in &layout.elements {
+                assert!(
+                    ids.insert(element.id()),
+                    "{options:?} repeats {}",
+                    element.id()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn elements_fit_the_layout() {
+        for options in all_options() {
+            let layout = generate(&options).unwrap();
+            for element in &layout.elements {
+                let rect = element.bounding_rect();
+                assert!(
+                    rect.min().x >= 0.0
+                        && rect.min().y >= 0.0
+                        && rect.max().x <= layout.width
+                        && rect.max().y <= layout.height,
+                    "element {} of {options:?} is outside the layout",
+                    element.id()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn enter_shape() {
+        for (standard, vertices) in [(Standard::Ansi, 4), (Standard::Iso, 6), (Standard::Jis, 6)] {
+            let layout = generate(&LayoutOptions {
+                standard,
+                ..LayoutOptions::default()
+            })
+            .unwrap();
+            let enter = layout
+                .elements
+                .iter()
+                .find_map(|element| match element {
+                    BoardElement::KeyboardKey(def) if def.key_codes == [0x0D] => Some(def),
+                    _ => None,
+                })
+                .unwrap();
+            assert_eq!(enter.boundaries.len(), vertices, "{standard}");
+        }
+    }
+
+    #[test]
+    fn jis_legends() {
+        let layout = generate(&LayoutOptions {
+            standard: Standard::Jis,
+            ..LayoutOptions::default()
+        })
+        .unwrap();
+        let keys: Vec<_> = layout
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                BoardElement::KeyboardKey(def) => Some(def),
+                _ => None,
+            })
+            .collect();
+        // The legend of the key right of the one with `keycode`, in the same row
+        let right_of = |keycode: u32| {
+            let key = keys.iter().find(|def| def.key_codes == [keycode]).unwrap();
+            let top = key.boundaries[0].y;
+            keys.iter()
+                .filter(|def| {
+                    def.boundaries[0].y == top && def.boundaries[0].x > key.boundaries[0].x
+                })
+                .min_by_key(|def| def.boundaries[0].x)
+                .map(|def| def.text.as_str())
+                .unwrap()
+        };
+        assert_eq!(right_of(0x30), "-");
+        assert_eq!(right_of(0xBD), "^");
+        assert_eq!(right_of(0xBB), "¥");
+        assert_eq!(right_of(0x50), "@");
+        assert_eq!(right_of(0xDB), "[");
+        assert_eq!(right_of(0x4C), ";");
+        assert_eq!(right_of(0xBA), ":");
+        assert_eq!(right_of(0xDE), "]");
+        assert_eq!(right_of(0xBF), "\\");
+    }
+
+    #[test]
+    fn invalid_sizes_are_rejected() {
+        for (unit, gap) in [
+            (0.0, 0.0),
+            (-44.0, 1.0),
+            (f32::NAN, 1.0),
+            (44.0, -1.0),
+            (44.0, 44.0),
+            (44.0, f32::NAN),
+        ] {
+            let options = LayoutOptions {
+                unit,
+                gap,
+                ..LayoutOptions::default()
+            };
+            assert!(generate(&options).is_err(), "unit {unit}, gap {gap}");
+        }
+        assert!(generate(&LayoutOptions {
+            gap: 0.0,
+            ..LayoutOptions::default()
+        })
+        .is_ok());
+    }
+}
@@ -0,0 +1,299 @@
+//! Key legends for Windows virtual-key codes, as produced by the input listener.
+//!
+//! Keycodes identify a physical key by what it types on a US keyboard, so the same keycode gets a
+//! different legend depending on the locale. Keys that US and ISO keyboards don't have, such as
+//! the Yen and Ro keys on JIS keyboards, get codes that no other key uses.
+
+/// Keyboard languages that legends can be generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    Us,
+    Uk,
+    /// ISO German (QWERTZ).
+    De,
+    /// French AZERTY.
+    Fr,
+    /// Japanese (JIS).
+    Jp,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 5] = [Locale::Us, Locale::Uk, Locale::De, Locale::Fr, Locale::Jp];
+
+    /// Legends that differ from the US layout, as `(keycode, text, shift_text)`.
+    fn overrides(&self) -> &'static [(u32, &'static str, &'static str)] {
+        match self {
+            Locale::Us => &[],
+            Locale::Uk => UK,
+            Locale::De => DE,
+            Locale::Fr => FR,
+            Locale::Jp => JP,
+        }
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Locale::Us => write!(f, "US"),
+            Locale::Uk => write!(f, "UK"),
+            Locale::De => write!(f, "German (QWERTZ)"),
+            Locale::Fr => write!(f, "French (AZERTY)"),
+            Locale::Jp => write!(f, "Japanese (JIS)"),
+        }
+    }
+}
+
+/// Returns the `(text, shift_text)` legends of the key with the given keycode, or `None` if the
+/// keycode is unknown.
+pub fn label(keycode: u32, locale: Locale) -> Option<(String, String)> {
+    if let Some((_, text, shift_text)) = locale
+        .overrides()
+        .iter()
+        .find(|(code, _, _)| *code == keycode)
+    {
+        return Some((text.to_string(), shift_text.to_string()));
+    }
+
+    let same = |label: &str| Some((label.to_string(), label.to_string()));
+    match keycode {
+        // Letters
+        0x41..=0x5A => {
+            let letter = char::from_u32(keycode).unwrap();
+            Some((letter.to_ascii_lowercase().to_string(), letter.to_string()))
+        }
+        // Number row
+        0x30..=0x39 => {
+            let digit = (keycode - 0x30) as usize;
+            Some((
+                digit.to_string(),
+                [")", "!", "@", "#", "$", "%", "^", "&", "*", "("][digit].to_string(),
+            ))
+        }
+        // Numpad digits
+        0x60..=0x69 => same(&(keycode - 0x60).to_string()),
+        // Function keys
+        0x70..=0x87 => same(&format!("F{}", keycode - 0x6F)),
+        0x08 => same("Backspace"),
+        0x09 => same("Tab"),
+        0x0D => same("Enter"),
+        0x10 | 0xA0 | 0xA1 => same("Shift"),
+        0x11 | 0xA2 | 0xA3 => same("Ctrl"),
+        0x12 | 0xA4 | 0xA5 => same("Alt"),
+        0x13 => same("Pause"),
+        0x14 => same("Caps Lock"),
+        0x15 => same("Kana"),
+        0x1C => same("Henkan"),
+        0x1D => same("Muhenkan"),
+        0x1B => same("Esc"),
+        0x20 => same("Space"),
+        0x21 => same("PgUp"),
+        0x22 => same("PgDn"),
+        0x23 => same("End"),
+        0x24 => same("Home"),
+        0x25 => same("←"),
+        0x26 => same("↑"),
+        0x27 => same("→"),
+        0x28 => same("↓"),
+        0x2C => same("PrtSc"),
+        0x2D => same("Ins"),
+        0x2E => same("Del"),
+        0x5B | 0x5C => same("Win"),
+        0x5D => same("Menu"),
+        0x6A => same("*"),
+        0x6B => same("+"),
+        0x6D => same("-"),
+        0x6E => same("."),
+        0x6F => same("/"),
+        0x90 => same("Num Lock"),
+        0x91 => same("Scroll Lock"),
+        0xBA => Some((";".into(), ":".into())),
+        0xBB => Some(("=".into(), "+".into())),
+        0xBC => Some((",".into(), "<".into())),
+        0xBD => Some(("-".into(), "_".into())),
+        0xBE => Some((".".into(), ">".into())),
+        0xBF => Some(("/".into(), "?".into())),
+        0xC0 => Some(("`".into(), "~".into())),
+        0xDB => Some(("[".into(), "{".into())),
+        0xDC => Some(("\\".into(), "|".into())),
+        0xDD => Some(("]".into(), "}".into())),
+        0xDE => Some(("'".into(), "\"".into())),
+        // The extra key next to left Shift on ISO keyboards
+        0xE2 => Some(("\\".into(), "|".into())),
+        0xF3 => same("Zenkaku"),
+        _ => None,
+    }
+}
+
+const UK: &[(u32, &str, &str)] = &[
+    (0x32, "2", "\""),
+    (0x33, "3", "£"),
+    (0xC0, "`", "¬"),
+    (0xDE, "'", "@"),
+    // On ISO keyboards, this key is next to Enter
+    (0xDC, "#", "~"),
+];
+
+const DE: &[(u32, &str, &str)] = &[
+    (0x59, "z", "Z"),
+    (0x5A, "y", "Y"),
+    (0x32, "2", "\""),
+    (0x33, "3", "§"),
+    (0x36, "6", "&"),
+    (0x37, "7", "/"),
+    (0x38, "8", "("),
+    (0x39, "9", ")"),
+    (0x30, "0", "="),
+    (0xBA, "ö", "Ö"),
+    (0xBB, "´", "`"),
+    (0xBC, ",", ";"),
+    (0xBD, "ß", "?"),
+    (0xBE, ".", ":"),
+    (0xBF, "-", "_"),
+    (0xC0, "^", "°"),
+    (0xDB, "ü", "Ü"),
+    (0xDC, "#", "'"),
+    (0xDD, "+", "*"),
+    (0xDE, "ä", "Ä"),
+    (0xE2, "<", ">"),
+    (0x11, "Strg", "Strg"),
+    (0xA2, "Strg", "Strg"),
+    (0xA3, "Strg", "Strg"),
+    (0xA5, "AltGr", "AltGr"),
+    (0x2C, "Druck", "Druck"),
+    (0x2D, "Einfg", "Einfg"),
+    (0x2E, "Entf", "Entf"),
+    (0x24, "Pos1", "Pos1"),
+    (0x23, "Ende", "Ende"),
+    (0x21, "Bild↑", "Bild↑"),
+    (0x22, "Bild↓", "Bild↓"),
+];
+
+const FR: &[(u32, &str, &str)] = &[
+    (0x41, "q", "Q"),
+    (0x51, "a", "A"),
+    (0x57, "z", "Z"),
+    (0x5A, "w", "W"),
+    (0x4D, ",", "?"),
+    (0x31, "&", "1"),
+    (0x32, "é", "2"),
+    (0x33, "\"", "3"),
+    (0x34, "'", "4"),
+    (0x35, "(", "5"),
+    (0x36, "-", "6"),
+    (0x37, "è", "7"),
+    (0x38, "_", "8"),
+    (0x39, "ç", "9"),
+    (0x30, "à", "0"),
+    (0xBA, "m", "M"),
+    (0xBB, "=", "+"),
+    (0xBC, ";", "."),
+    (0xBD, ")", "°"),
+    (0xBE, ":", "/"),
+    (0xBF, "!", "§"),
+    (0xC0, "²", "²"),
+    (0xDB, "^", "¨"),
+    (0xDC, "*", "µ"),
+    (0xDD, "$", "£"),
+    (0xDE, "ù", "%"),
+    (0xE2, "<", ">"),
+    (0xA5, "AltGr", "AltGr"),
+    (0x0D, "Entrée", "Entrée"),
+    (0x14, "Verr Maj", "Verr Maj"),
+    (0x1B, "Échap", "Échap"),
+    (0x2D, "Inser", "Inser"),
+    (0x2E, "Suppr", "Suppr"),
+    (0x23, "Fin", "Fin"),
+];
+
+const JP: &[(u32, &str, &str)] = &[
+    (0x32, "2", "\""),
+    (0x36, "6", "&"),
+    (0x37, "7", "'"),
+    (0x38, "8", "("),
+    (0x39, "9", ")"),
+    (0x30, "0", "0"),
+    (0xBA, ";", "+"),
+    (0xBB, "^", "~"),
+    (0xBD, "-", "="),
+    (0xC0, "半/全", "半/全"),
+    (0xDB, "@", "`"),
+    (0xDC, "]", "}"),
+    (0xDD, "[", "{"),
+    (0xDE, ":", "*"),
+    // Yen, left of Backspace
+    (0xDF, "¥", "|"),
+    // Ro, left of right Shift
+    (0xC1, "\\", "_"),
+    (0x15, "かな", "かな"),
+    (0x1C, "変換", "変換"),
+    (0x1D, "無変換", "無変換"),
+    (0xF3, "半/全", "半/全"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn text(keycode: u32, locale: Locale) -> String {
+        label(keycode, locale).unwrap().0
+    }
+
+    #[test]
+    fn overrides_are_unique() {
+        for locale in Locale::ALL {
+            let mut seen = HashSet::new();
+            for (keycode, _, _) in locale.overrides() {
+                assert!(
+                    seen.insert(keycode),
+                    "{locale}: {keycode:#04X} is listed twice"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn us_legends() {
+        assert_eq!(label(0x41, Locale::Us), Some(("a".into(), "A".into())));
+        assert_eq!(label(0x32, Locale::Us), Some(("2".into(), "@".into())));
+        assert_eq!(label(0x70, Locale::Us), Some(("F1".into(), "F1".into())));
+        assert_eq!(label(0x87, Locale::Us), Some(("F24".into(), "F24".into())));
+        assert_eq!(label(0x65, Locale::Us), Some(("5".into(), "5".into())));
+        assert_eq!(label(0xBA, Locale::Us), Some((";".into(), ":".into())));
+    }
+
+    #[test]
+    fn unknown_keycodes_have_no_legend() {
+        for locale in Locale::ALL {
+            assert_eq!(label(0x00, locale), None);
+            assert_eq!(label(0xFF, locale), None);
+        }
+    }
+
+    #[test]
+    fn locales_follow_physical_positions() {
+        assert_eq!(text(0x59, Locale::De), "z");
+        assert_eq!(text(0x5A, Locale::De), "y");
+        assert_eq!(text(0xBA, Locale::De), "ö");
+        assert_eq!(text(0x41, Locale::Fr), "q");
+        assert_eq!(text(0x51, Locale::Fr), "a");
+        assert_eq!(text(0x57, Locale::Fr), "z");
+        assert_eq!(text(0x5A, Locale::Fr), "w");
+        assert_eq!(text(0xBA, Locale::Fr), "m");
+        assert_eq!(text(0xBA, Locale::Jp), ";");
+        assert_eq!(text(0xDB, Locale::Jp), "@");
+        assert_eq!(text(0xDF, Locale::Jp), "¥");
+        assert_eq!(text(0xC1, Locale::Jp), "\\");
+        assert_eq!(label(0x33, Locale::Uk), Some(("3".into(), "£".into())));
+    }
+
+    #[test]
+    fn locales_fall_back_to_us() {
+        for locale in Locale::ALL {
+            assert_eq!(text(0x08, locale), "Backspace");
+            assert_eq!(text(0x46, locale), "f");
+        }
+    }
+}
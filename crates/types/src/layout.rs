@@ -1,9 +1,15 @@
-use geo::Coord;
+use geo::{AffineTransform, BoundingRect, Coord, LineString, Polygon, Rect};
 pub use ordered_float::OrderedFloat;
 use schemars::{json_schema, JsonSchema};
 use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
 
-#[derive(Serialize, Deserialize, Default, Debug, JsonSchema)]
+/// Number of line segments used to approximate each rounded corner.
+const CORNER_SEGMENTS: usize = 8;
+/// Number of line segments used to approximate an ellipse.
+const ELLIPSE_SEGMENTS: usize = 64;
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, JsonSchema)]
 #[serde(rename_all = "PascalCase")]
 pub struct Layout {
     /// No actual meaning. Kept for parity with NohBoard layout files.
@@ -15,6 +21,16 @@ pub struct Layout {
     pub elements: Vec<BoardElement>,
 }
 
+impl Layout {
+    /// Converts rounded corners and ellipses into plain polygons, so that NohBoard can display
+    /// the layout.
+    pub fn tessellate(&mut self) {
+        for element in &mut self.elements {
+            element.tessellate();
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(tag = "__type")]
 /// Union for different element types
@@ -36,6 +52,13 @@ impl BoardElement {
         }
     }
 
+    pub fn id_mut(&mut self) -> &mut u32 {
+        match CommonDefinitionMut::try_from(self) {
+            Ok(def) => def.id,
+            Err(def) => &mut def.id,
+        }
+    }
+
     pub fn translate(&mut self, delta: Coord<f32>, move_text: bool) {
         match self {
             BoardElement::MouseSpeedIndicator(key) => {
@@ -52,6 +75,94 @@ impl BoardElement {
             }
         }
     }
+
+    /// Applies `transform` to the element's boundaries and text position. Mouse speed indicators
+    /// stay circular, and corners stay round, so their radius is scaled by the transform's average
+    /// scale factor instead. Ellipses are fitted to their bounding rectangle, so they're turned into
+    /// polygons first if the transform doesn't keep them axis-aligned.
+    pub fn affine_transform(&mut self, transform: &AffineTransform<f32>) {
+        let determinant = transform.a() * transform.e() - transform.b() * transform.d();
+        let scale = determinant.abs().sqrt();
+        // Rotations by multiples of 90° aren't exact
+        let zero = |value: f32| value.abs() < 1e-5;
+        let axis_aligned = (zero(transform.b()) && zero(transform.d()))
+            || (zero(transform.a()) && zero(transform.e()));
+        if let Ok(def) = CommonDefinitionMut::try_from(&mut *self)
+            && *def.shape == KeyShape::Ellipse
+            && !axis_aligned
+        {
+            self.tessellate();
+        }
+        match self {
+            BoardElement::MouseSpeedIndicator(key) => {
+                key.location = transform.apply(key.location.clone().into()).into();
+                key.radius *= scale;
+            }
+            _ => {
+                let common = CommonDefinitionMut::try_from(self).unwrap();
+                for boundary in common.boundaries {
+                    *boundary = transform.apply(boundary.clone().into()).into();
+                }
+                *common.text_position = transform.apply(common.text_position.clone().into()).into();
+                *common.corner_radius *= scale;
+            }
+        }
+    }
+
+    /// Replaces the element's boundaries with its outline, and removes its rounded corners and
+    /// ellipse shape.
+    pub fn tessellate(&mut self) {
+        let Ok(def) = CommonDefinitionMut::try_from(self) else {
+            return;
+        };
+        if *def.shape == KeyShape::Polygon && *def.corner_radius <= 0.0 {
+            return;
+        }
+        let outline = outline(def.boundaries, *def.corner_radius, *def.shape);
+        *def.boundaries = outline.exterior().coords().map(|c| (*c).into()).collect();
+        // geo closes rings by repeating the first point
+        def.boundaries.pop();
+        *def.corner_radius = 0.0;
+        *def.shape = KeyShape::Polygon;
+    }
+
+    /// Returns the smallest axis-aligned rectangle containing the element.
+    pub fn bounding_rect(&self) -> Rect<f32> {
+        match CommonDefinitionRef::try_from(self) {
+            Ok(def) => LineString::from(def.boundaries.clone())
+                .bounding_rect()
+                .unwrap_or_else(|| Rect::new(def.text_position.clone(), def.text_position.clone())),
+            Err(def) => {
+                let center = Coord::from(def.location.clone());
+                let radius = Coord {
+                    x: def.radius,
+                    y: def.radius,
+                };
+                Rect::new(center - radius, center + radius)
+            }
+        }
+    }
+}
+
+/// Returns the smallest axis-aligned rectangle containing all of the given elements.
+pub fn group_bounding_rect<'a>(
+    elements: impl IntoIterator<Item = &'a BoardElement>,
+) -> Option<Rect<f32>> {
+    elements
+        .into_iter()
+        .map(BoardElement::bounding_rect)
+        .reduce(|a, b| {
+            Rect::new(
+                Coord {
+                    x: a.min().x.min(b.min().x),
+                    y: a.min().y.min(b.min().y),
+                },
+                Coord {
+                    x: a.max().x.max(b.max().x),
+                    y: a.max().y.max(b.max().y),
+                },
+            )
+        })
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
@@ -67,6 +178,12 @@ pub struct KeyboardKeyDefinition {
     pub text_position: SerializablePoint,
     pub key_codes: Vec<u32>,
     pub text: String,
+    /// Radius of the element's rounded corners. Not supported by NohBoard.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub corner_radius: f32,
+    /// Not supported by NohBoard.
+    #[serde(default, skip_serializing_if = "KeyShape::is_polygon")]
+    pub shape: KeyShape,
     /// Text to display when Shift is held.
     pub shift_text: String,
     pub change_on_caps: bool,
@@ -85,6 +202,31 @@ pub struct CommonDefinition {
     pub text_position: SerializablePoint,
     pub key_codes: Vec<u32>,
     pub text: String,
+    /// Radius of the element's rounded corners. Not supported by NohBoard.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub corner_radius: f32,
+    /// Not supported by NohBoard.
+    #[serde(default, skip_serializing_if = "KeyShape::is_polygon")]
+    pub shape: KeyShape,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+pub enum KeyShape {
+    /// The polygon described by the boundaries.
+    #[default]
+    Polygon,
+    /// An ellipse filling the bounding rectangle of the boundaries.
+    Ellipse,
+}
+
+impl KeyShape {
+    fn is_polygon(&self) -> bool {
+        *self == KeyShape::Polygon
+    }
+}
+
+fn is_zero(value: &f32) -> bool {
+    *value == 0.0
 }
 
 impl CommonDefinition {
@@ -106,6 +248,8 @@ impl From<KeyboardKeyDefinition> for CommonDefinition {
             text_position: val.text_position,
             boundaries: val.boundaries,
             key_codes: val.key_codes,
+            corner_radius: val.corner_radius,
+            shape: val.shape,
         }
     }
 }
@@ -128,6 +272,8 @@ pub struct CommonDefinitionRef<'a> {
     pub text_position: &'a SerializablePoint,
     pub boundaries: &'a Vec<SerializablePoint>,
     pub key_codes: &'a Vec<u32>,
+    pub corner_radius: &'a f32,
+    pub shape: &'a KeyShape,
 }
 
 impl<'a> From<&'a KeyboardKeyDefinition> for CommonDefinitionRef<'a> {
@@ -138,6 +284,8 @@ impl<'a> From<&'a KeyboardKeyDefinition> for CommonDefinitionRef<'a> {
             text_position: &val.text_position,
             boundaries: &val.boundaries,
             key_codes: &val.key_codes,
+            corner_radius: &val.corner_radius,
+            shape: &val.shape,
         }
     }
 }
@@ -150,6 +298,8 @@ impl<'a> From<&'a CommonDefinition> for CommonDefinitionRef<'a> {
             text_position: &val.text_position,
             boundaries: &val.boundaries,
             key_codes: &val.key_codes,
+            corner_radius: &val.corner_radius,
+            shape: &val.shape,
         }
     }
 }
@@ -166,12 +316,105 @@ impl<'a> TryFrom<&'a BoardElement> for CommonDefinitionRef<'a> {
     }
 }
 
+impl CommonDefinitionRef<'_> {
+    /// Returns the shape that is drawn and hit-tested, with rounded corners and ellipses
+    /// approximated by line segments.
+    pub fn outline(&self) -> Polygon<f32> {
+        outline(self.boundaries, *self.corner_radius, *self.shape)
+    }
+}
+
+fn outline(boundaries: &[SerializablePoint], corner_radius: f32, shape: KeyShape) -> Polygon<f32> {
+    let points: Vec<Coord<f32>> = boundaries.iter().cloned().map(Coord::from).collect();
+    let outline = match shape {
+        KeyShape::Ellipse => match LineString::from(points.clone()).bounding_rect() {
+            Some(rect) => ellipse(rect),
+            None => points,
+        },
+        KeyShape::Polygon if corner_radius > 0.0 => round_corners(&points, corner_radius),
+        KeyShape::Polygon => points,
+    };
+    Polygon::new(LineString::from(outline), vec![])
+}
+
+fn ellipse(rect: Rect<f32>) -> Vec<Coord<f32>> {
+    let center = rect.center();
+    (0..ELLIPSE_SEGMENTS)
+        .map(|i| {
+            let angle = i as f32 / ELLIPSE_SEGMENTS as f32 * TAU;
+            Coord {
+                x: center.x + rect.width() / 2.0 * angle.cos(),
+                y: center.y + rect.height() / 2.0 * angle.sin(),
+            }
+        })
+        .collect()
+}
+
+/// Replaces each corner with a circular arc of the given radius. The radius is reduced at corners
+/// whose edges are too short to fit it.
+fn round_corners(points: &[Coord<f32>], radius: f32) -> Vec<Coord<f32>> {
+    // A closing point would leave the first corner with a zero-length edge, so it wouldn't be
+    // rounded
+    let points = match points {
+        [first, .., last] if first == last => &points[..points.len() - 1],
+        _ => points,
+    };
+    let len = points.len();
+    let mut out = Vec::with_capacity(len * (CORNER_SEGMENTS + 1));
+    for (i, &corner) in points.iter().enumerate() {
+        let to_previous = points[(i + len - 1) % len] - corner;
+        let to_next = points[(i + 1) % len] - corner;
+        let previous_len = to_previous.x.hypot(to_previous.y);
+        let next_len = to_next.x.hypot(to_next.y);
+        if previous_len == 0.0 || next_len == 0.0 {
+            out.push(corner);
+            continue;
+        }
+        let u = to_previous / previous_len;
+        let v = to_next / next_len;
+        let angle = (u.x * v.x + u.y * v.y).clamp(-1.0, 1.0).acos();
+        // Straight and degenerate corners have nothing to round
+        if !(f32::EPSILON..PI - 1e-3).contains(&angle) {
+            out.push(corner);
+            continue;
+        }
+
+        let half_tan = (angle / 2.0).tan();
+        // How far from the corner the arc meets each edge
+        let distance = (radius / half_tan).min(previous_len.min(next_len) / 2.0);
+        let radius = distance * half_tan;
+        let bisector = u + v;
+        let bisector = bisector / bisector.x.hypot(bisector.y);
+        let center = corner + bisector * (radius / (angle / 2.0).sin());
+        let start = corner + u * distance;
+        let end = corner + v * distance;
+
+        let start_angle = (start.y - center.y).atan2(start.x - center.x);
+        let mut sweep = (end.y - center.y).atan2(end.x - center.x) - start_angle;
+        if sweep > PI {
+            sweep -= TAU;
+        } else if sweep < -PI {
+            sweep += TAU;
+        }
+        for step in 0..=CORNER_SEGMENTS {
+            let angle = start_angle + sweep * step as f32 / CORNER_SEGMENTS as f32;
+            out.push(Coord {
+                x: center.x + radius * angle.cos(),
+                y: center.y + radius * angle.sin(),
+            });
+        }
+    }
+    out
+}
+
 pub struct CommonDefinitionMut<'a> {
     pub id: &'a mut u32,
     pub text: &'a mut String,
     pub text_position: &'a mut SerializablePoint,
     pub boundaries: &'a mut Vec<SerializablePoint>,
     pub key_codes: &'a mut Vec<u32>,
+    pub corner_radius: &'a mut f32,
+    pub shape: &'a mut KeyShape,
 }
 
 impl CommonDefinitionMut<'_> {
@@ -193,6 +436,8 @@ impl<'a> From<&'a mut KeyboardKeyDefinition> for CommonDefinitionMut<'a> {
             text_position: &mut val.text_position,
             boundaries: &mut val.boundaries,
             key_codes: &mut val.key_codes,
+            corner_radius: &mut val.corner_radius,
+            shape: &mut val.shape,
         }
     }
 }
@@ -205,6 +450,8 @@ impl<'a> From<&'a mut CommonDefinition> for CommonDefinitionMut<'a> {
             text_position: &mut val.text_position,
             boundaries: &mut val.boundaries,
             key_codes: &mut val.key_codes,
+            corner_radius: &mut val.corner_radius,
+            shape: &mut val.shape,
         }
     }
 }
@@ -320,3 +567,135 @@ impl std::fmt::Display for SerializablePoint {
         write!(f, "({}, {})", self.x, self.y)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::Area;
+
+    fn rectangle(closed: bool) -> Vec<Coord<f32>> {
+        let mut points = vec![
+            Coord { x: 0.0, y: 0.0 },
+            Coord { x: 40.0, y: 0.0 },
+            Coord { x: 40.0, y: 20.0 },
+            Coord { x: 0.0, y: 20.0 },
+        ];
+        if closed {
+            points.push(points[0]);
+        }
+        points
+    }
+
+    #[test]
+    fn closed_rectangle_rounds_every_corner() {
+        for closed in [false, true] {
+            let rounded = round_corners(&rectangle(closed), 5.0);
+            assert_eq!(rounded.len(), 4 * (CORNER_SEGMENTS + 1), "closed: {closed}");
+            for corner in rectangle(false) {
+                assert!(!rounded.contains(&corner), "{corner:?} wasn't rounded");
+            }
+        }
+    }
+
+    #[test]
+    fn corner_radius_is_limited_by_short_edges() {
+        // The 20px edges only fit a radius of 10
+        let rounded = Polygon::new(
+            LineString::from(round_corners(&rectangle(false), 50.0)),
+            vec![],
+        );
+        let rect = rounded.bounding_rect().unwrap();
+        assert!((rect.width() - 40.0).abs() < 0.01, "{rect:?}");
+        assert!((rect.height() - 20.0).abs() < 0.01, "{rect:?}");
+        // The arcs' segments cut off a little area
+        let expected = 800.0 - (4.0 - PI) * 100.0;
+        assert!((rounded.unsigned_area() - expected).abs() < 3.0);
+    }
+
+    #[test]
+    fn outline_matches_shape() {
+        let boundaries: Vec<SerializablePoint> =
+            rectangle(false).into_iter().map(Into::into).collect();
+
+        let polygon = outline(&boundaries, 0.0, KeyShape::Polygon);
+        assert_eq!(polygon.exterior().0[..4], rectangle(false));
+
+        let rounded = outline(&boundaries, 5.0, KeyShape::Polygon);
+        let expected = 800.0 - (4.0 - PI) * 25.0;
+        assert!((rounded.unsigned_area() - expected).abs() < 1.0);
+
+        let ellipse = outline(&boundaries, 0.0, KeyShape::Ellipse);
+        assert_eq!(ellipse.bounding_rect(), polygon.bounding_rect());
+        let expected = PI * 20.0 * 10.0;
+        assert!((ellipse.unsigned_area() - expected).abs() < 2.0);
+    }
+
+    #[test]
+    fn tessellate_turns_shapes_into_polygons() {
+        let mut layout = Layout {
+            elements: vec![
+                key(rectangle(false), 5.0, KeyShape::Polygon),
+                key(rectangle(false), 0.0, KeyShape::Ellipse),
+                key(rectangle(false), 0.0, KeyShape::Polygon),
+            ],
+            ..Default::default()
+        };
+        let outlines: Vec<_> = layout
+            .elements
+            .iter()
+            .map(|element| CommonDefinitionRef::try_from(element).unwrap().outline())
+            .collect();
+        layout.tessellate();
+        for (element, outline) in layout.elements.iter().zip(outlines) {
+            let def = CommonDefinitionRef::try_from(element).unwrap();
+            assert_eq!(*def.shape, KeyShape::Polygon);
+            assert_eq!(*def.corner_radius, 0.0);
+            assert_ne!(def.boundaries.first(), def.boundaries.last());
+            assert_eq!(def.outline(), outline);
+        }
+        let plain = CommonDefinitionRef::try_from(&layout.elements[2]).unwrap();
+        assert_eq!(plain.boundaries.len(), 4);
+    }
+
+    fn key(points: Vec<Coord<f32>>, corner_radius: f32, shape: KeyShape) -> BoardElement {
+        BoardElement::MouseKey(CommonDefinition {
+            id: 0,
+            boundaries: points.into_iter().map(Into::into).collect(),
+            text_position: Coord { x: 0.0, y: 0.0 }.into(),
+            key_codes: vec![0],
+            text: String::new(),
+            corner_radius,
+            shape,
+        })
+    }
+
+    #[test]
+    fn rotated_ellipse_keeps_its_size() {
+        let mut element = key(rectangle(false), 0.0, KeyShape::Ellipse);
+        let center = Coord { x: 20.0, y: 10.0 };
+        element.affine_transform(&AffineTransform::rotate(90.0, center));
+        let def = CommonDefinitionRef::try_from(&element).unwrap();
+        assert_eq!(*def.shape, KeyShape::Ellipse);
+        let rect = element.bounding_rect();
+        assert!((rect.width() - 20.0).abs() < 0.01, "{rect:?}");
+        assert!((rect.height() - 40.0).abs() < 0.01, "{rect:?}");
+
+        let mut element = key(rectangle(false), 0.0, KeyShape::Ellipse);
+        element.affine_transform(&AffineTransform::rotate(45.0, center));
+        let def = CommonDefinitionRef::try_from(&element).unwrap();
+        assert_eq!(*def.shape, KeyShape::Polygon);
+        // An ellipse with radii 20 and 10 rotated by 45° spans 2 * sqrt((20² + 10²) / 2)
+        let expected = 2.0 * ((400.0f32 + 100.0) / 2.0).sqrt();
+        let rect = element.bounding_rect();
+        assert!((rect.width() - expected).abs() < 0.1, "{rect:?}");
+        assert!((rect.height() - expected).abs() < 0.1, "{rect:?}");
+    }
+
+    #[test]
+    fn scaling_scales_corner_radius() {
+        let mut element = key(rectangle(false), 5.0, KeyShape::Polygon);
+        element.affine_transform(&AffineTransform::scale(2.0, 2.0, Coord { x: 0.0, y: 0.0 }));
+        let def = CommonDefinitionRef::try_from(&element).unwrap();
+        assert_eq!(*def.corner_radius, 10.0);
+    }
+}
@@ -1,3 +1,6 @@
+pub mod clipboard;
+pub mod generator;
+pub mod labels;
 pub mod layout;
 pub mod settings;
 pub mod style;
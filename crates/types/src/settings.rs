@@ -1,3 +1,4 @@
+use crate::layout::SerializablePoint;
 use display_info::DisplayInfo;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
@@ -9,10 +10,21 @@ pub struct Settings {
     pub follow_for_caps_insensitive: bool,
     /// Name of the category to use.
     pub category: String,
-    #[serde(alias = "keyboard")]
-    pub layout_index: usize,
-    /// Index of the style to use.
-    pub style: usize,
+    /// Name of the layout to use, within the category.
+    #[serde(default, rename = "layout_name")]
+    pub layout: Option<String>,
+    /// Name of the style to use, prefixed with `global/` for global styles. `None` selects the
+    /// global default style.
+    #[serde(default, rename = "style_name")]
+    pub style: Option<String>,
+    /// Index of the layout to use, as saved by older versions. Only read to migrate to
+    /// [`Settings::layout`].
+    #[serde(default, alias = "keyboard", skip_serializing)]
+    pub layout_index: Option<usize>,
+    /// Index of the style to use, as saved by older versions. Only read to migrate to
+    /// [`Settings::style`].
+    #[serde(default, rename = "style", skip_serializing)]
+    pub style_index: Option<usize>,
     /// Whether to treat the cursor's distance from the center of the screen as its velocity.
     pub mouse_from_center: bool,
     pub mouse_sensitivity: f32,
@@ -24,6 +36,22 @@ pub struct Settings {
     /// Whether to update the text position of an element when it is moved in the graphical
     /// editor.
     pub update_text_position: bool,
+    /// How elements snap into place when dragged in the graphical editor.
+    #[serde(default)]
+    pub snapping: Snapping,
+    /// Windows that were open when NuhxBoard last exited. They are reopened on startup.
+    #[serde(default)]
+    pub open_windows: Vec<OpenWindow>,
+    /// Whether NuhxBoard was in edit mode when it last exited. It starts in edit mode if so.
+    #[serde(default)]
+    pub edit_mode: bool,
+    /// Number of previous versions to keep as `.bak` files when saving a layout or style.
+    #[serde(default = "default_backups")]
+    pub backups: u32,
+}
+
+fn default_backups() -> u32 {
+    3
 }
 
 impl Default for Settings {
@@ -41,8 +69,10 @@ impl Default for Settings {
             follow_for_caps_sensitive: false,
             follow_for_caps_insensitive: false,
             category: String::new(),
-            layout_index: 0,
-            style: 0,
+            layout: None,
+            style: None,
+            layout_index: None,
+            style_index: None,
             mouse_from_center: false,
             mouse_sensitivity: 50.0,
             min_press_time: 0,
@@ -53,10 +83,46 @@ impl Default for Settings {
                 primary: true,
             },
             update_text_position: true,
+            snapping: Snapping::default(),
+            open_windows: Vec::new(),
+            edit_mode: false,
+            backups: default_backups(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct Snapping {
+    /// Whether to snap dragged elements to the grid.
+    pub grid: bool,
+    /// Number of grid cells along each side of a newly created key.
+    pub subdivisions: u32,
+    /// Whether to snap the edges and centers of dragged elements to those of other elements.
+    pub elements: bool,
+}
+
+impl Default for Snapping {
+    fn default() -> Self {
+        Self {
+            grid: true,
+            subdivisions: 4,
+            elements: true,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct OpenWindow {
+    /// The window's id, as given by `iced_multi_window::Window::id`.
+    pub id: String,
+    /// The last known position of the window's top-left corner.
+    pub position: Option<SerializablePoint>,
+    /// The id of the element the window was for, for windows that refer to elements by index.
+    /// Those are restored for the element with this id, wherever it is in the layout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub element: Option<u32>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
 pub enum Capitalization {
     Lower,
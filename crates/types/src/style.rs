@@ -11,7 +11,7 @@ use std::{
 };
 use tracing::debug;
 
-#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 #[serde(rename_all = "PascalCase")]
 pub struct Style {
     /// Background color of the window. Will be overridden by background image if present.
@@ -23,6 +23,28 @@ pub struct Style {
     pub element_styles: HashMap<u32, ElementStyle>,
 }
 
+impl Style {
+    /// Returns the names of the images the style uses, which are in the category's `images`
+    /// directory. Empty names, which mean no image, are left out.
+    pub fn image_file_names(&self) -> impl Iterator<Item = &String> {
+        let key_styles = self
+            .element_styles
+            .values()
+            .filter_map(|s| s.as_key_style())
+            .flat_map(|s| [s.loose.as_ref(), s.pressed.as_ref()])
+            .flatten();
+        [
+            &self.default_key_style.loose,
+            &self.default_key_style.pressed,
+        ]
+        .into_iter()
+        .chain(key_styles)
+        .filter_map(|s| s.background_image_file_name.as_ref())
+        .chain(&self.background_image_file_name)
+        .filter(|name| !name.is_empty())
+    }
+}
+
 // This allows `HashMap<u32, ElementStyle>` to be serialized as a list of `{Key: u32, Value: ElementStyle}`
 struct CustomMap;
 impl CustomMap {
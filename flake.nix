@@ -35,7 +35,11 @@
         craneLib = (crane.mkLib pkgs).overrideToolchain rustToolchain;
 
         iconFilter = path: (builtins.match ".*NuhxBoard.png$" path) != null;
-        iconOrCargo = path: type: (iconFilter path) || (craneLib.filterCargoSources path type);
+        # The sample keyboards are built into the binary
+        keyboardsFilter = path: (builtins.match ".*keyboards.zip$" path) != null;
+        iconOrCargo =
+          path: type:
+          (iconFilter path) || (keyboardsFilter path) || (craneLib.filterCargoSources path type);
         src = lib.cleanSourceWith {
           src = ./.;
           filter = iconOrCargo;
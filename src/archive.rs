@@ -0,0 +1,571 @@
+//! Zipped keyboards, such as the sample keyboards and keyboard packs.
+//!
+//! A keyboard pack mirrors the layout of the keyboards directory, holding one category with some
+//! of its keyboards, the images they use, and global styles, along with a [`Manifest`] listing
+//! them.
+
+use crate::{
+    nuhxboard::{copy_dir, write_file},
+    types::KeyboardItem,
+};
+use nuhxboard_types::{layout::Layout, style::Style};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{self, Read, Seek, Write},
+    path::Path,
+};
+use tracing::{debug, debug_span, info};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+const MANIFEST: &str = "manifest.json";
+const PACK_VERSION: u32 = 1;
+
+/// Lists what's in a keyboard pack.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub category: String,
+    pub keyboards: Vec<String>,
+    pub global_styles: Vec<String>,
+}
+
+/// What to do with keyboards, images and global styles in a pack that already exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PackConflicts {
+    /// Imports the pack into a new category if its category exists, and renames global styles
+    /// that exist.
+    #[default]
+    KeepBoth,
+    Overwrite,
+    Skip,
+}
+
+impl PackConflicts {
+    pub const ALL: [PackConflicts; 3] = [
+        PackConflicts::KeepBoth,
+        PackConflicts::Overwrite,
+        PackConflicts::Skip,
+    ];
+}
+
+impl std::fmt::Display for PackConflicts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackConflicts::KeepBoth => write!(f, "Keep both"),
+            PackConflicts::Overwrite => write!(f, "Overwrite"),
+            PackConflicts::Skip => write!(f, "Skip"),
+        }
+    }
+}
+
+/// Extracts the zip archive read from `reader` into the directory `to`, creating it if needed.
+/// Entries that would end up outside of `to` are skipped.
+pub fn extract(reader: impl Read + Seek, to: &Path) -> io::Result<()> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let span = debug_span!("unzip");
+    let _guard = span.enter();
+    fs::create_dir_all(to)?;
+    let len = archive.len();
+    for i in 0..len {
+        let mut file = archive.by_index(i)?;
+        let Some(path) = file.enclosed_name() else {
+            continue;
+        };
+        let outpath = to.join(path);
+        debug!("{} ({}/{len})", outpath.display(), i + 1);
+
+        let with_path = |e: io::Error| io::Error::new(e.kind(), format!("{outpath:?}: {e}"));
+        if file.is_dir() {
+            fs::create_dir_all(&outpath).map_err(with_path)?;
+        } else {
+            if let Some(parent) = outpath.parent() {
+                fs::create_dir_all(parent).map_err(with_path)?;
+            }
+            let mut outfile = File::create(&outpath).map_err(with_path)?;
+            io::copy(&mut file, &mut outfile).map_err(with_path)?;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            if let Some(mode) = file.unix_mode() {
+                fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))
+                    .map_err(with_path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn invalid_pack(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_style(path: &Path) -> io::Result<Style> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// Writes a pack of the given keyboards in `category` and global styles from the keyboards
+/// directory `keyboards_dir` to `to`. Only the images that the styles use are included.
+pub fn export_pack(
+    keyboards_dir: &Path,
+    category: &str,
+    keyboards: &[String],
+    global_styles: &[String],
+    to: &Path,
+) -> io::Result<()> {
+    let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    let add_file = |zip: &mut ZipWriter<_>, name: String, path: &Path| -> io::Result<()> {
+        debug!(name, "Adding file to pack");
+        zip.start_file(name, options)?;
+        zip.write_all(&fs::read(path)?)
+    };
+
+    let mut images = BTreeSet::new();
+    for keyboard in keyboards {
+        let dir = keyboards_dir.join(category).join(keyboard);
+        add_file(
+            &mut zip,
+            format!("{category}/{keyboard}/keyboard.json"),
+            &dir.join("keyboard.json"),
+        )?;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "style") {
+                continue;
+            }
+            images.extend(read_style(&path)?.image_file_names().cloned());
+            let file_name = path.file_name().unwrap().to_string_lossy();
+            add_file(
+                &mut zip,
+                format!("{category}/{keyboard}/{file_name}"),
+                &path,
+            )?;
+        }
+    }
+    for name in global_styles {
+        let path = keyboards_dir.join("global").join(format!("{name}.style"));
+        images.extend(read_style(&path)?.image_file_names().cloned());
+        add_file(&mut zip, format!("global/{name}.style"), &path)?;
+    }
+    for image in images {
+        let path = keyboards_dir.join(category).join("images").join(&image);
+        // Styles can refer to images that don't exist, which NuhxBoard ignores
+        if path.is_file() {
+            add_file(&mut zip, format!("{category}/images/{image}"), &path)?;
+        }
+    }
+
+    zip.start_file(MANIFEST, options)?;
+    let manifest = Manifest {
+        version: PACK_VERSION,
+        category: category.to_owned(),
+        keyboards: keyboards.to_vec(),
+        global_styles: global_styles.to_vec(),
+    };
+    serde_json::to_writer_pretty(&mut zip, &manifest)?;
+    let contents = zip.finish()?.into_inner();
+    write_file(to, &contents, 0)
+}
+
+/// Returns the global styles made for `category` in the keyboards directory `keyboards_dir`,
+/// which are those that use images and find all of them in the category's `images` directory.
+/// Styles without images aren't tied to a category, so they're left out.
+pub fn category_global_styles(keyboards_dir: &Path, category: &str) -> io::Result<Vec<String>> {
+    let global = keyboards_dir.join("global");
+    let images = keyboards_dir.join(category).join("images");
+    if !global.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut styles = Vec::new();
+    for entry in fs::read_dir(&global)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "style") {
+            continue;
+        }
+        let style = read_style(&path)?;
+        let mut image_names = style.image_file_names().peekable();
+        if image_names.peek().is_some() && image_names.all(|name| images.join(name).is_file()) {
+            styles.push(path.file_stem().unwrap().to_string_lossy().into_owned());
+        }
+    }
+    styles.sort();
+    Ok(styles)
+}
+
+/// Imports the pack at `from` into the keyboards directory `keyboards_dir`, and returns the
+/// category it was imported into.
+pub fn import_pack(
+    keyboards_dir: &Path,
+    from: &Path,
+    conflicts: PackConflicts,
+) -> io::Result<String> {
+    // The pack is extracted next to the keyboards directory and checked before anything is
+    // imported
+    let staging = keyboards_dir.with_file_name(".pack.partial");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    let result = File::open(from)
+        .and_then(|file| extract(file, &staging))
+        .and_then(|_| read_manifest(&staging))
+        .and_then(|manifest| install_pack(keyboards_dir, &staging, &manifest, conflicts));
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// Checks that the file at `path` in the pack extracted into `staging` holds a valid `T`.
+fn check_file<T: DeserializeOwned>(staging: &Path, path: &Path) -> io::Result<()> {
+    let name = path.strip_prefix(staging).unwrap_or(path).display();
+    let contents = fs::read(path).map_err(|e| invalid_pack(format!("{name}: {e}")))?;
+    serde_json::from_slice::<T>(&contents).map_err(|e| invalid_pack(format!("{name}: {e}")))?;
+    Ok(())
+}
+
+/// Reads the manifest of the pack extracted into `staging`, and checks that everything it lists
+/// is in the pack.
+fn read_manifest(staging: &Path) -> io::Result<Manifest> {
+    let manifest: Manifest = match fs::read(staging.join(MANIFEST)) {
+        Ok(contents) => serde_json::from_slice(&contents)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(invalid_pack(format!(
+                "Not a keyboard pack, as it has no {MANIFEST}"
+            )));
+        }
+        Err(e) => return Err(e),
+    };
+    if manifest.version > PACK_VERSION {
+        return Err(invalid_pack(format!(
+            "The pack was made by a newer version of NuhxBoard (pack version {})",
+            manifest.version
+        )));
+    }
+    let names = std::iter::once(&manifest.category)
+        .chain(&manifest.keyboards)
+        .chain(&manifest.global_styles);
+    for name in names {
+        if !KeyboardItem::is_valid_name(name) {
+            return Err(invalid_pack(format!(
+                "The pack has an invalid name: {name:?}"
+            )));
+        }
+    }
+
+    for keyboard in &manifest.keyboards {
+        let dir = staging.join(&manifest.category).join(keyboard);
+        check_file::<Layout>(staging, &dir.join("keyboard.json"))?;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "style") {
+                check_file::<Style>(staging, &path)?;
+            }
+        }
+    }
+    for name in &manifest.global_styles {
+        let path = staging.join("global").join(format!("{name}.style"));
+        check_file::<Style>(staging, &path)?;
+    }
+    Ok(manifest)
+}
+
+/// Returns the first of `name (2)`, `name (3)` and so on that isn't taken.
+fn free_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    (2..)
+        .map(|n| format!("{name} ({n})"))
+        .find(|name| !taken(name))
+        .unwrap()
+}
+
+/// Copies the directory `from` to `to`, replacing what's there only once the copy is complete.
+fn replace_dir(from: &Path, to: &Path) -> io::Result<()> {
+    let name = to.file_name().unwrap().to_string_lossy();
+    let temp = to.with_file_name(format!(".{name}.partial"));
+    let old = to.with_file_name(format!(".{name}.old"));
+    for dir in [&temp, &old] {
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+    }
+    if let Err(e) = copy_dir(from, &temp) {
+        let _ = fs::remove_dir_all(&temp);
+        return Err(e);
+    }
+    if to.exists() {
+        fs::rename(to, &old)?;
+    }
+    fs::rename(&temp, to)?;
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+    Ok(())
+}
+
+/// Copies what `manifest` lists from the pack extracted into `staging` into the keyboards
+/// directory, and returns the category it went into.
+fn install_pack(
+    keyboards_dir: &Path,
+    staging: &Path,
+    manifest: &Manifest,
+    conflicts: PackConflicts,
+) -> io::Result<String> {
+    let mut category = manifest.category.clone();
+    if conflicts == PackConflicts::KeepBoth && keyboards_dir.join(&category).exists() {
+        category = free_name(&category, |name| keyboards_dir.join(name).exists());
+    }
+    info!(category, ?manifest, "Importing keyboard pack");
+    let to = keyboards_dir.join(&category);
+    let new_category = !to.exists();
+
+    let result = install_category(&staging.join(&manifest.category), &to, manifest, conflicts)
+        .and_then(|_| {
+            install_global_styles(
+                &staging.join("global"),
+                &keyboards_dir.join("global"),
+                manifest,
+                conflicts,
+            )
+        });
+    // A category made for the pack is removed again rather than left half imported
+    if result.is_err() && new_category {
+        let _ = fs::remove_dir_all(&to);
+    }
+    result.map(|_| category)
+}
+
+fn install_category(
+    from: &Path,
+    to: &Path,
+    manifest: &Manifest,
+    conflicts: PackConflicts,
+) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for keyboard in &manifest.keyboards {
+        let target = to.join(keyboard);
+        // Packs only go into an existing category when overwriting or skipping, so with
+        // `KeepBoth` the keyboard is listed more than once
+        if target.exists() && conflicts != PackConflicts::Overwrite {
+            continue;
+        }
+        replace_dir(&from.join(keyboard), &target)?;
+    }
+
+    if from.join("images").is_dir() {
+        for entry in fs::read_dir(from.join("images"))? {
+            let entry = entry?;
+            let target = to.join("images").join(entry.file_name());
+            if entry.file_type()?.is_file()
+                && !(conflicts == PackConflicts::Skip && target.exists())
+            {
+                write_file(&target, &fs::read(entry.path())?, 0)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn install_global_styles(
+    from: &Path,
+    to: &Path,
+    manifest: &Manifest,
+    conflicts: PackConflicts,
+) -> io::Result<()> {
+    let style_path = |name: &str| to.join(format!("{name}.style"));
+    for name in &manifest.global_styles {
+        let target = match conflicts {
+            PackConflicts::Skip if style_path(name).exists() => continue,
+            PackConflicts::KeepBoth if style_path(name).exists() => {
+                style_path(&free_name(name, |name| style_path(name).exists()))
+            }
+            _ => style_path(name),
+        };
+        let contents = fs::read(from.join(format!("{name}.style")))?;
+        write_file(&target, &contents, 0)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Returns an empty keyboards directory in a temporary directory of its own.
+    fn keyboards_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nuhxboard-{test}-{}", std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        let keyboards = dir.join("keyboards");
+        fs::create_dir_all(&keyboards).unwrap();
+        keyboards
+    }
+
+    fn write_json(path: &Path, value: &impl Serialize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, serde_json::to_vec(value).unwrap()).unwrap();
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, contents) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// Adds the keyboard `Category/Board` with a style using `bg.png`, and a global style using
+    /// it too.
+    fn add_keyboard(keyboards: &Path) {
+        let style = Style {
+            background_image_file_name: Some("bg.png".to_owned()),
+            ..Style::default()
+        };
+        write_json(
+            &keyboards.join("Category/Board/keyboard.json"),
+            &Layout::default(),
+        );
+        write_json(&keyboards.join("Category/Board/Dark.style"), &style);
+        write_json(&keyboards.join("global/Shared.style"), &style);
+        write_json(&keyboards.join("global/Plain.style"), &Style::default());
+        fs::create_dir_all(keyboards.join("Category/images")).unwrap();
+        fs::write(keyboards.join("Category/images/bg.png"), b"png").unwrap();
+        fs::write(keyboards.join("Category/images/unused.png"), b"png").unwrap();
+    }
+
+    /// Exports `Category/Board` and the global styles using the category's images.
+    fn export(keyboards: &Path) -> PathBuf {
+        let global_styles = category_global_styles(keyboards, "Category").unwrap();
+        assert_eq!(global_styles, ["Shared"]);
+        let pack = keyboards.with_file_name("pack.zip");
+        export_pack(
+            keyboards,
+            "Category",
+            &["Board".to_owned()],
+            &global_styles,
+            &pack,
+        )
+        .unwrap();
+        pack
+    }
+
+    #[test]
+    fn round_trip() {
+        let from = keyboards_dir("round-trip-from");
+        add_keyboard(&from);
+        let pack = export(&from);
+
+        let to = keyboards_dir("round-trip-to");
+        let category = import_pack(&to, &pack, PackConflicts::KeepBoth).unwrap();
+        assert_eq!(category, "Category");
+        for file in [
+            "Category/Board/keyboard.json",
+            "Category/Board/Dark.style",
+            "Category/images/bg.png",
+            "global/Shared.style",
+        ] {
+            assert!(to.join(file).is_file(), "{file} wasn't imported");
+        }
+        assert!(!to.join("Category/images/unused.png").exists());
+        assert!(!to.join("global/Plain.style").exists());
+        assert!(!to.with_file_name(".pack.partial").exists());
+    }
+
+    #[test]
+    fn conflicts() {
+        let keyboards = keyboards_dir("conflicts");
+        add_keyboard(&keyboards);
+        let pack = export(&keyboards);
+        let layout = keyboards.join("Category/Board/keyboard.json");
+
+        let category = import_pack(&keyboards, &pack, PackConflicts::KeepBoth).unwrap();
+        assert_eq!(category, "Category (2)");
+        assert!(keyboards.join("Category (2)/Board/keyboard.json").is_file());
+        assert!(keyboards.join("global/Shared (2).style").is_file());
+
+        fs::write(&layout, b"edited").unwrap();
+        import_pack(&keyboards, &pack, PackConflicts::Skip).unwrap();
+        assert_eq!(fs::read(&layout).unwrap(), b"edited");
+
+        import_pack(&keyboards, &pack, PackConflicts::Overwrite).unwrap();
+        assert_ne!(fs::read(&layout).unwrap(), b"edited");
+        assert!(!keyboards.join("Category (3)").exists());
+    }
+
+    #[test]
+    fn missing_keyboard_changes_nothing() {
+        let keyboards = keyboards_dir("missing-keyboard");
+        add_keyboard(&keyboards);
+        let manifest = serde_json::to_vec(&Manifest {
+            version: PACK_VERSION,
+            category: "Category".to_owned(),
+            keyboards: vec!["Board".to_owned()],
+            global_styles: Vec::new(),
+        })
+        .unwrap();
+        let pack = keyboards.with_file_name("pack.zip");
+        write_zip(&pack, &[(MANIFEST, &manifest)]);
+
+        let error = import_pack(&keyboards, &pack, PackConflicts::Overwrite).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(keyboards.join("Category/Board/keyboard.json").is_file());
+    }
+
+    #[test]
+    fn entries_outside_are_skipped() {
+        let keyboards = keyboards_dir("outside");
+        let manifest = serde_json::to_vec(&Manifest {
+            version: PACK_VERSION,
+            category: "Category".to_owned(),
+            keyboards: Vec::new(),
+            global_styles: Vec::new(),
+        })
+        .unwrap();
+        let pack = keyboards.with_file_name("pack.zip");
+        write_zip(
+            &pack,
+            &[
+                (MANIFEST, &manifest),
+                ("../outside.txt", b"outside"),
+                ("Category/../../outside2.txt", b"outside"),
+            ],
+        );
+
+        import_pack(&keyboards, &pack, PackConflicts::KeepBoth).unwrap();
+        let dir = keyboards.parent().unwrap();
+        assert!(!dir.join("outside.txt").exists());
+        assert!(!dir.join("outside2.txt").exists());
+    }
+
+    #[test]
+    fn not_a_pack() {
+        let keyboards = keyboards_dir("not-a-pack");
+        let pack = keyboards.with_file_name("pack.zip");
+        write_zip(&pack, &[("Category/Board/keyboard.json", b"{}")]);
+
+        let error = import_pack(&keyboards, &pack, PackConflicts::KeepBoth).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!keyboards.join("Category").exists());
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        let keyboards = keyboards_dir("invalid-names");
+        let manifest = serde_json::to_vec(&Manifest {
+            version: PACK_VERSION,
+            category: "..".to_owned(),
+            keyboards: Vec::new(),
+            global_styles: Vec::new(),
+        })
+        .unwrap();
+        let pack = keyboards.with_file_name("pack.zip");
+        write_zip(&pack, &[(MANIFEST, &manifest)]);
+
+        let error = import_pack(&keyboards, &pack, PackConflicts::KeepBoth).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
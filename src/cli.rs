@@ -0,0 +1,365 @@
+use crate::{nuhxboard::write_file, ui::keyboard::BALL_TO_RADIUS_RATIO};
+use clap::{Args, Subcommand};
+use color_eyre::eyre::{eyre, Context};
+use geo::{Contains, Distance, Euclidean, Point};
+use image::{imageops::FilterType, ImageFormat, Rgba, RgbaImage};
+use nuhxboard_types::{
+    generator::{self, FormFactor, LayoutOptions, Standard},
+    layout::{BoardElement, CommonDefinitionRef, Layout},
+    style::{KeySubStyle, NohRgb, Style},
+};
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// What to load on startup instead of what was loaded last time.
+#[derive(Args, Debug, Clone, Default)]
+pub struct Launch {
+    /// Keyboard category to load
+    #[arg(long)]
+    pub category: Option<String>,
+    /// Keyboard layout to load, by name
+    #[arg(long)]
+    pub layout: Option<String>,
+    /// Style to load, by name. Global styles are given as global/NAME
+    #[arg(long)]
+    pub style: Option<String>,
+    /// Starts in edit mode
+    #[arg(long)]
+    pub edit: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Generates a standard keyboard layout
+    Generate {
+        /// One of none, numpad, 60, 65, 75, tkl or full
+        #[arg(long, default_value = "full")]
+        form_factor: FormFactor,
+        /// One of ansi, iso or jis
+        #[arg(long, default_value = "ansi")]
+        standard: Standard,
+        /// Size of a 1u key in pixels, including the gap between keys
+        #[arg(long, default_value_t = 44.0)]
+        unit: f32,
+        /// Space between adjacent keys in pixels
+        #[arg(long, default_value_t = 1.0)]
+        gap: f32,
+        /// Adds mouse buttons, scroll directions and a speed indicator below the keys
+        #[arg(long)]
+        mouse: bool,
+        /// File to write the layout to. Printed if not given
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Checks layouts, styles and keyboard directories for errors
+    Validate {
+        /// Layout files, .style files, or keyboard directories, whose styles are checked against
+        /// the layout
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Converts a layout to one that NohBoard can load, replacing rounded corners and ellipses
+    /// with polygons
+    Convert { input: PathBuf, output: PathBuf },
+    /// Renders a layout to a PNG image. Legends and key images aren't drawn
+    Render {
+        layout: PathBuf,
+        /// Style to render the layout with. The default style is used if not given
+        #[arg(long)]
+        style: Option<PathBuf>,
+        /// Renders every key as pressed
+        #[arg(long)]
+        pressed: bool,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+pub fn run(command: Command) -> color_eyre::Result<()> {
+    match command {
+        Command::Generate {
+            form_factor,
+            standard,
+            unit,
+            gap,
+            mouse,
+            output,
+        } => {
+            let layout = generator::generate(&LayoutOptions {
+                form_factor,
+                standard,
+                unit,
+                gap,
+                mouse,
+            })
+            .map_err(|e| eyre!("Can't generate a layout: {e}"))?;
+            match output {
+                Some(path) => write_layout(&layout, &path)?,
+                None => serde_json::to_writer_pretty(io::stdout(), &layout)
+                    .context("Failed to print layout")?,
+            }
+        }
+        Command::Validate { paths } => {
+            let mut problems = 0;
+            for path in paths {
+                let found = validate(&path);
+                if found.is_empty() {
+                    println!("{}: ok", path.display());
+                }
+                for problem in &found {
+                    println!("{}: {problem}", path.display());
+                }
+                problems += found.len();
+            }
+            if problems > 0 {
+                return Err(eyre!("Found {problems} problem(s)"));
+            }
+        }
+        Command::Convert { input, output } => {
+            let mut layout = read_layout(&input)?;
+            layout.tessellate();
+            write_layout(&layout, &output)?;
+        }
+        Command::Render {
+            layout,
+            style,
+            pressed,
+            output,
+        } => {
+            let layout_path = layout;
+            let layout = read_layout(&layout_path)?;
+            let style = match style {
+                Some(path) => read_style(&path)?,
+                None => Style::default(),
+            };
+            // Keyboards are kept in `[CATEGORY]/[KEYBOARD]/keyboard.json`, next to the
+            // category's images
+            let images = layout_path
+                .parent()
+                .and_then(Path::parent)
+                .map(|category| category.join("images"));
+            let image = render(&layout, &style, images.as_deref(), pressed)?;
+            let format = ImageFormat::from_path(&output)
+                .with_context(|| format!("Unknown image format for {output:?}"))?;
+            let mut contents = io::Cursor::new(Vec::new());
+            image
+                .write_to(&mut contents, format)
+                .context("Failed to encode image")?;
+            write_file(&output, contents.get_ref(), 0)
+                .with_context(|| format!("Failed to write image to {output:?}"))?;
+        }
+    }
+    Ok(())
+}
+
+fn read_layout(path: &Path) -> color_eyre::Result<Layout> {
+    let file = File::open(path).with_context(|| format!("Failed to open layout {path:?}"))?;
+    serde_json::from_reader(file).with_context(|| format!("Failed to parse layout {path:?}"))
+}
+
+fn read_style(path: &Path) -> color_eyre::Result<Style> {
+    let file = File::open(path).with_context(|| format!("Failed to open style {path:?}"))?;
+    serde_json::from_reader(file).with_context(|| format!("Failed to parse style {path:?}"))
+}
+
+/// Writes the layout to `path`, leaving whatever was there untouched if it fails.
+fn write_layout(layout: &Layout, path: &Path) -> color_eyre::Result<()> {
+    let contents = serde_json::to_vec_pretty(layout).context("Failed to serialize layout")?;
+    write_file(path, &contents, 0).with_context(|| format!("Failed to write layout to {path:?}"))
+}
+
+/// Returns the problems found in the layout, style or keyboard directory at `path`.
+fn validate(path: &Path) -> Vec<String> {
+    if path.is_dir() {
+        let layout = match read_layout(&path.join("keyboard.json")) {
+            Ok(layout) => layout,
+            Err(e) => return vec![format!("{e:#}")],
+        };
+        let mut problems = validate_layout(&layout);
+        let entries = match fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => return vec![format!("Failed to read directory: {e}")],
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let style_path = entry.path();
+            if style_path.extension().is_none_or(|ext| ext != "style") {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            match read_style(&style_path) {
+                Ok(style) => problems.extend(
+                    validate_style(&style, &layout, path.parent())
+                        .into_iter()
+                        .map(|problem| format!("{name}: {problem}")),
+                ),
+                Err(e) => problems.push(format!("{e:#}")),
+            }
+        }
+        problems
+    } else if path.extension().is_some_and(|ext| ext == "style") {
+        match read_style(path) {
+            Ok(_) => Vec::new(),
+            Err(e) => vec![format!("{e:#}")],
+        }
+    } else {
+        match read_layout(path) {
+            Ok(layout) => validate_layout(&layout),
+            Err(e) => vec![format!("{e:#}")],
+        }
+    }
+}
+
+fn validate_layout(layout: &Layout) -> Vec<String> {
+    let mut problems = Vec::new();
+    if layout.width <= 0.0 || layout.height <= 0.0 {
+        problems.push(format!(
+            "The window size {}x{} isn't positive",
+            layout.width, layout.height
+        ));
+    }
+    let mut ids = HashSet::new();
+    for element in &layout.elements {
+        let id = element.id();
+        if !ids.insert(id) {
+            problems.push(format!("The id {id} is used by more than one element"));
+        }
+        let rect = element.bounding_rect();
+        if rect.max().x < 0.0
+            || rect.max().y < 0.0
+            || rect.min().x > layout.width
+            || rect.min().y > layout.height
+        {
+            problems.push(format!("Element {id} is outside of the window"));
+        }
+        if let Ok(def) = CommonDefinitionRef::try_from(element) {
+            if def.boundaries.len() < 3 {
+                problems.push(format!("Element {id} has fewer than 3 boundaries"));
+            }
+            if def.key_codes.is_empty() {
+                problems.push(format!("Element {id} has no keycodes"));
+            }
+        }
+    }
+    problems
+}
+
+/// Checks that the style only refers to elements in the layout, and to images in the category's
+/// `images` directory.
+fn validate_style(style: &Style, layout: &Layout, category: Option<&Path>) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (id, element_style) in &style.element_styles {
+        let Some(element) = layout.elements.iter().find(|e| e.id() == *id) else {
+            problems.push(format!("Styles element {id}, which isn't in the layout"));
+            continue;
+        };
+        let is_indicator = matches!(element, BoardElement::MouseSpeedIndicator(_));
+        if is_indicator != element_style.as_mouse_speed_indicator_style().is_some() {
+            problems.push(format!(
+                "The style of element {id} doesn't match the element's type"
+            ));
+        }
+    }
+
+    let Some(category) = category else {
+        return problems;
+    };
+    for image in style.image_file_names() {
+        if !category.join("images").join(image).exists() {
+            problems.push(format!("The image {image} doesn't exist"));
+        }
+    }
+    problems
+}
+
+fn rgba(color: NohRgb) -> Rgba<u8> {
+    Rgba([
+        color.red.clamp(0.0, 255.0) as u8,
+        color.green.clamp(0.0, 255.0) as u8,
+        color.blue.clamp(0.0, 255.0) as u8,
+        255,
+    ])
+}
+
+/// Draws the keyboard's background and elements in their loose or pressed styles, sampling each
+/// pixel at its center.
+fn render(
+    layout: &Layout,
+    style: &Style,
+    images: Option<&Path>,
+    pressed: bool,
+) -> color_eyre::Result<RgbaImage> {
+    let width = layout.width.max(1.0) as u32;
+    let height = layout.height.max(1.0) as u32;
+    let mut image = RgbaImage::from_pixel(width, height, rgba(style.background_color));
+    if let Some(name) = &style.background_image_file_name
+        && !name.is_empty()
+        && let Some(images) = images
+    {
+        let path = images.join(name);
+        image = image::open(&path)
+            .with_context(|| format!("Failed to open background image {path:?}"))?
+            .resize_exact(width, height, FilterType::Nearest)
+            .to_rgba8();
+    }
+
+    for element in &layout.elements {
+        let rect = element.bounding_rect();
+        let x_range = rect.min().x.max(0.0) as u32..(rect.max().x.ceil() as u32).min(width);
+        let y_range = rect.min().y.max(0.0) as u32..(rect.max().y.ceil() as u32).min(height);
+        let pixels = y_range.flat_map(|y| x_range.clone().map(move |x| (x, y)));
+        match CommonDefinitionRef::try_from(element) {
+            Ok(def) => {
+                let element_style = style
+                    .element_styles
+                    .get(def.id)
+                    .and_then(|s| s.as_key_style());
+                let sub_style: &KeySubStyle = if pressed {
+                    element_style
+                        .and_then(|s| s.pressed.as_ref())
+                        .unwrap_or(&style.default_key_style.pressed)
+                } else {
+                    element_style
+                        .and_then(|s| s.loose.as_ref())
+                        .unwrap_or(&style.default_key_style.loose)
+                };
+                let outline = def.outline();
+                let half_width = sub_style.outline_width as f32 / 2.0;
+                for (x, y) in pixels {
+                    let point = Point::new(x as f32 + 0.5, y as f32 + 0.5);
+                    let inside = outline.contains(&point);
+                    let on_outline = sub_style.show_outline
+                        && Euclidean.distance(&point, outline.exterior()) <= half_width;
+                    if on_outline {
+                        image.put_pixel(x, y, rgba(sub_style.outline));
+                    } else if inside {
+                        image.put_pixel(x, y, rgba(sub_style.background));
+                    }
+                }
+            }
+            Err(def) => {
+                let indicator_style = style
+                    .element_styles
+                    .get(&def.id)
+                    .and_then(|s| s.as_mouse_speed_indicator_style())
+                    .unwrap_or(&style.default_mouse_speed_indicator_style);
+                let center = Point::from(geo::Coord::from(def.location.clone()));
+                let half_width = indicator_style.outline_width as f32 / 2.0;
+                for (x, y) in pixels {
+                    let point = Point::new(x as f32 + 0.5, y as f32 + 0.5);
+                    let distance = Euclidean.distance(&point, &center);
+                    // The ball in the middle, as drawn while the mouse is still
+                    let ball = distance <= def.radius * BALL_TO_RADIUS_RATIO;
+                    let ring = (distance - def.radius).abs() <= half_width;
+                    if ball || ring {
+                        image.put_pixel(x, y, rgba(indicator_style.inner_color));
+                    }
+                }
+            }
+        }
+    }
+    Ok(image)
+}
@@ -1,30 +1,61 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod archive;
+mod cli;
 mod message;
 mod nuhxboard;
+mod recovery;
 mod types;
 mod ui;
 
-use std::{
-    fs::{self, File},
-    io::{self, prelude::*},
-};
+use std::{fs, io, path::PathBuf};
 
 use clap::Parser;
-use color_eyre::eyre::{Context, eyre};
+use color_eyre::eyre::Context;
 use nuhxboard::*;
-use tracing::{Level, debug, debug_span, info};
+use tracing::{Level, debug, info, warn};
 use tracing_subscriber::{filter, prelude::*};
 
 #[derive(Parser)]
 struct Args {
     #[arg(long)]
     iced_tracing: bool,
+    /// Directory to keep the settings and keyboards in. Overrides NUHXBOARD_HOME and portable mode
+    #[arg(long, global = true)]
+    config_dir: Option<PathBuf>,
+    /// Name of the settings file to use, so that several instances can be set up differently
+    #[arg(long, global = true, value_parser = profile_name)]
+    settings_profile: Option<String>,
+    /// Don't download the sample keyboards when there are no keyboards, if they aren't built in
+    #[arg(long)]
+    no_download: bool,
+    #[command(flatten)]
+    launch: cli::Launch,
+    #[command(subcommand)]
+    command: Option<cli::Command>,
+}
+
+/// Checks that a settings profile name can be used in file names without leaving the directory
+/// they're in.
+fn profile_name(name: &str) -> Result<String, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        return Err("must be a file name, without path separators or `..`".to_string());
+    }
+    Ok(name.to_string())
 }
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
-    let args = Args::parse();
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            attach_console();
+            e.exit();
+        }
+    };
+    if args.command.is_some() {
+        attach_console();
+    }
     if !args.iced_tracing {
         let registry = tracing_subscriber::registry().with(tracing_subscriber::fmt::layer());
         let level = std::env::var("RUST_LOG").unwrap_or_default();
@@ -35,9 +66,22 @@ fn main() -> color_eyre::Result<()> {
         tracing_subscriber::fmt::init();
     }
 
-    let config_path = KEYBOARDS_PATH
-        .parent()
-        .ok_or_else(|| eyre!("Config lives at root?"))?;
+    if let Some(dir) = args.config_dir {
+        CONFIG_DIR.set(dir).unwrap();
+    }
+    if let Some(profile) = args.settings_profile {
+        SETTINGS_PROFILE.set(profile).unwrap();
+    }
+
+    if let Some(command) = args.command {
+        return cli::run(command);
+    }
+
+    recovery::install_panic_hook();
+
+    fs::create_dir_all(&*CACHE_PATH).context("Failed to create cache directory")?;
+    // Older versions kept the background image next to the settings
+    let _ = fs::remove_file(config_dir().join("background.png"));
 
     if !KEYBOARDS_PATH.exists() {
         fs::create_dir_all(&*KEYBOARDS_PATH).context("Failed to create config directory")?;
@@ -47,70 +91,11 @@ fn main() -> color_eyre::Result<()> {
         fs::create_dir_all(&*KEYBOARDS_PATH).context("Failed to create config directory")?;
     }
 
-    if fs::read_dir(&*KEYBOARDS_PATH)?.count() == 0 {
-        info!("Downloading sample keyboards");
-        let res = reqwest::blocking::get(
-            "https://raw.githubusercontent.com/justdeeevin/nuhxboard/main/keyboards.zip",
-        )
-        .context("Failed to download sample keyboards")?;
-
-        let mut keyboards_file = File::create_new(config_path.join("keyboards.zip"))
-            .context("Failed to create keyboards.zip")?;
-
-        keyboards_file
-            .write_all(
-                &res.bytes()
-                    .context("Failed to get bytes for keyboards.zip")?,
-            )
-            .context("Failed to write keyboards.zip")?;
-
-        let mut keyboards_archive =
-            zip::ZipArchive::new(keyboards_file).context("Failed to load keyboards.zip")?;
-
-        info!("Extracting sample keyboards");
-        let span = debug_span!("unzip");
-        let _guard = span.enter();
-        let len = keyboards_archive.len();
-        for i in 1..=len {
-            let mut file = keyboards_archive
-                .by_index(i - 1)
-                .with_context(|| format!("Failed to get file #{i} from zip"))?;
-            let outpath = match file.enclosed_name() {
-                Some(path) => KEYBOARDS_PATH.join(path),
-                None => continue,
-            };
-            debug!("{} ({i}/{len})", outpath.display());
-
-            if (*file.name()).ends_with('/') {
-                fs::create_dir_all(&outpath)
-                    .with_context(|| format!("Failed to create directory {outpath:?}"))?;
-            } else {
-                if let Some(p) = outpath.parent()
-                    && !p.exists()
-                {
-                    fs::create_dir_all(p)
-                        .with_context(|| format!("Failed to create directory {p:?}"))?;
-                }
-                let mut outfile = File::create(&outpath)
-                    .with_context(|| format!("Failed to create file {outpath:?}"))?;
-                io::copy(&mut file, &mut outfile)
-                    .with_context(|| format!("Failed to populate file {outpath:?} from zip"))?;
-            }
-
-            // Get and Set permissions
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-
-                if let Some(mode) = file.unix_mode() {
-                    fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))
-                        .with_context(|| format!("Failed to set permissions on {outpath:?}"))?;
-                }
-            }
-        }
-
-        fs::remove_file(config_path.join("keyboards.zip"))
-            .context("Failed to remove keyboards.zip")?;
+    if fs::read_dir(&*KEYBOARDS_PATH)?.count() == 0
+        && let Err(e) = install_sample_keyboards(args.no_download)
+    {
+        // NuhxBoard works without them, so this isn't fatal
+        warn!("Failed to install sample keyboards: {e:?}");
     }
 
     let global_path = KEYBOARDS_PATH.join("global");
@@ -120,12 +105,82 @@ fn main() -> color_eyre::Result<()> {
     }
 
     // Runs the app, initializing state using NuhxBoard::new
-    iced::daemon(NuhxBoard::new, NuhxBoard::update, NuhxBoard::view)
-        .title(NuhxBoard::title)
-        .theme(NuhxBoard::theme)
-        .subscription(NuhxBoard::subscription)
-        .font(iced_aw::ICED_AW_FONT_BYTES)
-        .run()?;
+    let launch = args.launch;
+    iced::daemon(
+        move || NuhxBoard::new(launch.clone()),
+        NuhxBoard::update,
+        NuhxBoard::view,
+    )
+    .title(NuhxBoard::title)
+    .theme(NuhxBoard::theme)
+    .subscription(NuhxBoard::subscription)
+    .font(iced_aw::ICED_AW_FONT_BYTES)
+    .run()?;
+
+    Ok(())
+}
 
+/// Release builds on Windows don't open a console, so subcommands and `--help` print to the one
+/// they were run from, if there is one.
+#[cfg(all(windows, not(debug_assertions)))]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // Fails if there's no console to attach to, in which case there's nowhere to print anyway
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(all(windows, not(debug_assertions))))]
+fn attach_console() {}
+
+/// The sample keyboards, if they're built into the binary.
+#[cfg(feature = "bundled-keyboards")]
+const BUNDLED_KEYBOARDS: Option<&[u8]> = Some(include_bytes!("../keyboards.zip"));
+#[cfg(not(feature = "bundled-keyboards"))]
+const BUNDLED_KEYBOARDS: Option<&[u8]> = None;
+
+const SAMPLE_KEYBOARDS_URL: &str =
+    "https://raw.githubusercontent.com/justdeeevin/nuhxboard/main/keyboards.zip";
+
+/// Fills the empty keyboards directory with the sample keyboards, using the ones built into the
+/// binary if there are any, or downloading them otherwise.
+fn install_sample_keyboards(no_download: bool) -> color_eyre::Result<()> {
+    let archive = match BUNDLED_KEYBOARDS {
+        Some(bundled) => {
+            info!("Extracting bundled sample keyboards");
+            bundled.to_vec()
+        }
+        None if no_download => {
+            info!("Not downloading sample keyboards");
+            return Ok(());
+        }
+        None => {
+            info!("Downloading sample keyboards");
+            reqwest::blocking::get(SAMPLE_KEYBOARDS_URL)
+                .and_then(|res| res.error_for_status())
+                .and_then(|res| res.bytes())
+                .context("Failed to download sample keyboards")?
+                .to_vec()
+        }
+    };
+
+    // The keyboards are extracted next to the keyboards directory and moved into place once
+    // they're complete, so that an interrupted or failed extraction leaves nothing behind
+    let staging = config_dir().join(".keyboards.partial");
+    if staging.exists() {
+        debug!("Removing partially extracted keyboards");
+        fs::remove_dir_all(&staging).context("Failed to remove partially extracted keyboards")?;
+    }
+    if let Err(e) = archive::extract(io::Cursor::new(archive), &staging) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e).context("Failed to extract sample keyboards");
+    }
+    fs::remove_dir(&*KEYBOARDS_PATH).context("Failed to replace keyboards directory")?;
+    fs::rename(&staging, &*KEYBOARDS_PATH).context("Failed to move sample keyboards into place")?;
     Ok(())
 }
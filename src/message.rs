@@ -1,20 +1,22 @@
-use crate::{nuhxboard::*, types::*, ui::popups::Action};
+use crate::{archive::PackConflicts, nuhxboard::*, types::*, ui::popups::Action};
 use geo::Coord;
 use iced::{window, Color, Theme};
 use iced_multi_window::Window;
 use nuhxboard_types::{
-    layout::SerializablePoint,
+    generator::{FormFactor, LayoutOptions, Standard},
+    labels::Locale,
+    layout::{BoardElement, KeyShape, SerializablePoint},
     settings::{Capitalization, DisplayChoice},
-    style::FontStyle,
+    style::{FontStyle, Style},
 };
 use rdevin::Event;
+use serde::{Deserialize, Serialize};
 
-use std::path::PathBuf;
+use std::{collections::BTreeSet, mem::Discriminant, path::PathBuf};
 
 #[derive(Clone, Debug)]
 pub enum Message {
     Open(Box<dyn Window<NuhxBoard, Theme, Message>>),
-    CloseAllOf(Box<dyn Window<NuhxBoard, Theme, Message>>),
     Exit,
     Closed(window::Id),
     CloseRequested,
@@ -23,12 +25,38 @@ pub enum Message {
     LoadStyle(usize),
     ChangeKeyboardCategory(String),
     LoadLayout(usize),
+    /// Creates a keyboard with a generated layout, creating the category if needed, and loads it.
+    CreateKeyboard {
+        category: String,
+        name: String,
+        options: LayoutOptions,
+        size: iced::Size,
+    },
+    RenameItem {
+        item: KeyboardItem,
+        name: String,
+    },
+    DuplicateItem {
+        item: KeyboardItem,
+        name: String,
+    },
+    DeleteItem(KeyboardItem),
+    /// Writes a keyboard pack of a category or keyboard to the given path.
+    ExportPack {
+        item: KeyboardItem,
+        path: PathBuf,
+    },
+    /// Imports the keyboard pack at the given path into the keyboards directory.
+    ImportPack {
+        path: PathBuf,
+        conflicts: PackConflicts,
+    },
     ChangeSetting(Setting),
     ChangeStyle(StyleSetting),
     ClearPressedKeys,
     ToggleEditMode,
-    MoveElement {
-        index: usize,
+    MoveElements {
+        indices: Vec<usize>,
         delta: Coord<f32>,
     },
     MoveFace {
@@ -49,6 +77,10 @@ pub enum Message {
     Undo,
     Redo,
     ToggleSaveStyleAsGlobal,
+    ToggleSaveLayoutAsNohBoard,
+    /// Saves a copy of the layout that NohBoard can display, with rounded corners and ellipses
+    /// tessellated into polygons.
+    ExportLayout(PathBuf),
     ChangeColor(ColorPicker, Color),
     ToggleColorPicker(ColorPicker),
     ChangeTextInput(TextInputType, String),
@@ -56,9 +88,70 @@ pub enum Message {
     ChangeSelection(usize, SelectionType, usize),
     SwapBoundaries(usize, usize, usize),
     UpdateHoveredElement(Option<usize>),
+    UpdateSelection(BTreeSet<usize>),
+    Align(Align),
+    Copy,
+    Cut,
+    /// Pastes elements from the clipboard, centered on the given point, or on the cursor if
+    /// `None`.
+    Paste(Option<iced::Point>),
+    PasteElements {
+        contents: Option<String>,
+        position: iced::Point,
+    },
+    Duplicate,
+    Nudge(Coord<f32>),
+    CycleSelection {
+        backwards: bool,
+    },
+    /// Splits face `face` of element `index` with a new vertex at `position`.
+    InsertVertex {
+        index: usize,
+        face: usize,
+        position: Coord<f32>,
+    },
+    RemoveVertex {
+        index: usize,
+        vertex: usize,
+    },
+    CombineShapes(ShapeOperation),
+    /// Starts assigning key presses to the selected keyboard keys, or to all of them if fewer
+    /// than two are selected, in reading order.
+    StartLearning,
+    StopLearning,
+    SkipLearning,
+    /// Makes the given element the next one to be assigned.
+    LearnElement(usize),
+    /// Sets the legends of the selected keyboard keys, or all of them if none are selected, from
+    /// their first keycode.
+    LabelKeys(Locale),
+    /// Like `LabelKeys`, but only for keys whose legends are still the ones for `from`.
+    RelabelKeys {
+        from: Locale,
+        to: Locale,
+    },
+    /// Transforms the selected elements around `pivot`, or around the center of the selection if
+    /// `None`.
+    TransformElements {
+        transform: ElementTransform,
+        pivot: Option<Coord<f32>>,
+    },
+    SetView {
+        zoom: f32,
+        pan: iced::Vector,
+    },
+    /// Saves both the definition and the style.
+    Save,
+    /// An editor shortcut pressed in the given window. Shortcuts are ignored while that window
+    /// or the main window, which they edit, is blocked by a modal dialog.
+    Shortcut(window::Id, Box<Message>),
     ChangeElement(usize, ElementProperty),
     CenterTextPosition(usize),
-    MakeRectangle(usize),
+    MakeRectangle {
+        index: usize,
+        position: Coord<f32>,
+        size: Coord<f32>,
+    },
     StartDetecting(usize),
     ClearCache(usize),
     ClearAllCaches,
@@ -72,9 +165,67 @@ pub enum Message {
         position: iced::Point,
         window_id: window::Id,
     },
+    WindowMoved {
+        position: iced::Point,
+        window_id: window::Id,
+    },
     RemoveElement,
     Commit(Action),
     CancelDiscard(Action),
+    /// Writes the unsaved edits to the recovery directory.
+    Autosave,
+    /// Restores the edits recovered from a previous run.
+    RestoreRecovery,
+    DiscardRecovery,
+    /// Routed to the window with the given Id, which handles it using its own state.
+    WindowUpdate(window::Id, WindowMessage),
+}
+
+#[derive(Debug, Clone)]
+pub enum WindowMessage {
+    /// Closes the window instead of being routed to it.
+    Close,
+    RectanglePositionX(f32),
+    RectanglePositionY(f32),
+    RectangleSizeX(f32),
+    RectangleSizeY(f32),
+    TransformAngle(f32),
+    TransformScale(f32),
+    TransformCustomPivot(bool),
+    TransformPivotX(f32),
+    TransformPivotY(f32),
+    LabelLocale(Locale),
+    NewKeyboardCategory(String),
+    NewKeyboardName(String),
+    NewKeyboardFormFactor(FormFactor),
+    NewKeyboardStandard(Standard),
+    NewKeyboardUnit(f32),
+    NewKeyboardGap(f32),
+    NewKeyboardMouse(bool),
+    NewKeyboardWidth(f32),
+    NewKeyboardHeight(f32),
+    ItemName(String),
+    PackPath(String),
+    PackConflicts(PackConflicts),
+}
+
+/// How to combine the selected elements' shapes. The result replaces the first selected element,
+/// and the others are removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapeOperation {
+    Union,
+    /// Subtracts the other elements from the first.
+    Subtract,
+    Intersect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ElementTransform {
+    /// Rotates clockwise by the given number of degrees.
+    Rotate(f32),
+    Scale(f32),
+    FlipHorizontally,
+    FlipVertically,
 }
 
 #[derive(Debug, Clone)]
@@ -95,6 +246,8 @@ pub enum ElementProperty {
     MouseSpeedIndicatorPositionX(f32),
     MouseSpeedIndicatorPositionY(f32),
     MouseSpeedIndicatorRadius(f32),
+    CornerRadius(f32),
+    Shape(KeyShape),
 }
 
 #[derive(Debug, Clone)]
@@ -122,23 +275,156 @@ pub enum StyleSetting {
     MouseSpeedIndicatorOutlineWidth { id: u32, width: u32 },
 }
 
-#[derive(Debug, Clone)]
-#[allow(clippy::enum_variant_names)]
-pub enum Change {
-    MoveElement {
+/// What an edit made from the UI changed. Consecutive edits to the same target, such as typing
+/// into a text input or dragging a color picker, are coalesced into a single change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditTarget {
+    Element {
         index: usize,
+        property: Discriminant<ElementProperty>,
+        /// The boundary or keycode edited, for properties that are lists.
+        item: Option<usize>,
+    },
+    Style {
+        setting: Discriminant<StyleSetting>,
+        /// The element whose style is edited, for settings that aren't defaults.
+        id: Option<u32>,
+    },
+    Color(ColorPicker),
+}
+
+impl EditTarget {
+    /// The target of changing `property` of the element at `index`. Toggles have none, as
+    /// coalescing them could cancel them out.
+    pub fn element(index: usize, property: &ElementProperty) -> Option<Self> {
+        let item = match property {
+            ElementProperty::FollowCaps | ElementProperty::Shape(_) => return None,
+            ElementProperty::Boundary(item, _) | ElementProperty::Keycode(item, _) => Some(*item),
+            _ => None,
+        };
+        Some(Self::Element {
+            index,
+            property: std::mem::discriminant(property),
+            item,
+        })
+    }
+
+    /// The target of changing `setting`. Toggles have none, as coalescing them could cancel them
+    /// out.
+    pub fn style(setting: &StyleSetting) -> Option<Self> {
+        let id = match setting {
+            StyleSetting::DefaultLooseKeyShowOutline
+            | StyleSetting::DefaultPressedKeyShowOutline
+            | StyleSetting::LooseKeyShowOutline(_)
+            | StyleSetting::PressedKeyShowOutline(_)
+            | StyleSetting::LooseKeyFontStyle { .. }
+            | StyleSetting::PressedKeyFontStyle { .. } => return None,
+            StyleSetting::LooseKeyFontFamily(id)
+            | StyleSetting::LooseKeyBackgroundImage(id)
+            | StyleSetting::PressedKeyFontFamily(id)
+            | StyleSetting::PressedKeyBackgroundImage(id)
+            | StyleSetting::LooseKeyOutlineWidth { id, .. }
+            | StyleSetting::PressedKeyOutlineWidth { id, .. }
+            | StyleSetting::MouseSpeedIndicatorOutlineWidth { id, .. } => Some(*id),
+            _ => None,
+        };
+        Some(Self::Style {
+            setting: std::mem::discriminant(setting),
+            id,
+        })
+    }
+}
+
+/// An undoable edit to the layout or style. Changes are serialized along with unsaved edits for
+/// crash recovery.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Change {
+    MoveElements {
+        indices: Vec<usize>,
+        #[serde(with = "CoordDef")]
         delta: Coord<f32>,
     },
     MoveFace {
         index: usize,
         face: usize,
+        #[serde(with = "CoordDef")]
         delta: Coord<f32>,
     },
     MoveVertex {
         index: usize,
         vertex: usize,
+        #[serde(with = "CoordDef")]
         delta: Coord<f32>,
     },
+    InsertVertex {
+        index: usize,
+        vertex: usize,
+        position: SerializablePoint,
+    },
+    RemoveVertex {
+        index: usize,
+        vertex: usize,
+        position: SerializablePoint,
+    },
+    /// Any other edit to a single element, such as changing one of its properties.
+    EditElement {
+        index: usize,
+        before: Box<BoardElement>,
+        after: Box<BoardElement>,
+    },
+    AddElement {
+        index: usize,
+        element: Box<BoardElement>,
+    },
+    RemoveElement {
+        index: usize,
+        element: Box<BoardElement>,
+    },
+    Resize {
+        #[serde(with = "SizeDef")]
+        before: iced::Size,
+        #[serde(with = "SizeDef")]
+        after: iced::Size,
+    },
+    /// Any edit to the style. The whole style is stored, as edits to the defaults affect every
+    /// element.
+    EditStyle {
+        before: Box<Style>,
+        after: Box<Style>,
+    },
+    /// Several changes that are undone and redone together.
+    Group(Vec<Change>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Coord<f32>")]
+struct CoordDef {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "iced::Size")]
+struct SizeDef {
+    width: f32,
+    height: f32,
+}
+
+/// How to arrange the selected elements relative to each other.
+#[derive(Debug, Clone, Copy)]
+pub enum Align {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// Lines up the elements' centers on a vertical axis.
+    CenterHorizontally,
+    /// Lines up the elements' centers on a horizontal axis.
+    CenterVertically,
+    /// Spaces the elements evenly between the leftmost and rightmost ones.
+    DistributeHorizontally,
+    /// Spaces the elements evenly between the topmost and bottommost ones.
+    DistributeVertically,
 }
 
 #[derive(Debug, Clone)]
@@ -153,6 +439,10 @@ pub enum Setting {
     FollowForCapsSensitive,
     FollowForCapsInsensitive,
     UpdateTextPosition,
+    SnapToGrid,
+    GridSubdivisions(u32),
+    SnapToElements,
+    Backups(u32),
 }
 
 impl Message {
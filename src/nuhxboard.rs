@@ -1,21 +1,28 @@
 use crate::{
+    archive,
+    cli::Launch,
     message::*,
+    recovery::{self, Snapshot},
     types::*,
     ui::{app::*, popups::*},
 };
 use display_info::DisplayInfo;
-use geo::{Centroid, Coord, CoordsIter, LineString, Polygon, Rect};
+use etcetera::BaseStrategy;
+use geo::{
+    AffineTransform, BooleanOps, Centroid, Coord, CoordsIter, LineString, MultiPolygon, Polygon,
+    Rect,
+};
 use iced::{
-    Renderer, Subscription, Task, Theme,
-    advanced::{graphics::core::SmolStr, subscription},
-    widget::canvas::Cache,
-    window,
+    Renderer, Subscription, Task, Theme, advanced::subscription, widget::canvas::Cache, window,
 };
-use iced_multi_window::WindowManager;
+use iced_multi_window::{Window, WindowManager};
 use image::ImageReader;
 use nalgebra::Vector2;
 use nuhxboard_logic::{listener::RdevinSubscriber, mouse_button_code_convert};
 use nuhxboard_types::{
+    clipboard::ClipboardElements,
+    generator,
+    labels::{self, Locale},
     layout::*,
     settings::*,
     style::{self, *},
@@ -25,12 +32,14 @@ use smol::Timer;
 use std::{
     collections::{BTreeSet, HashMap},
     fs::{self, File},
-    path::PathBuf,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Write,
+    path::{Path, PathBuf},
     rc::Rc,
-    sync::{Arc, LazyLock},
+    sync::{Arc, LazyLock, OnceLock},
     time::{Duration, Instant},
 };
-use tracing::{debug, info, info_span, instrument, trace};
+use tracing::{debug, info, info_span, instrument, trace, warn};
 
 macro_rules! key_style_change {
     ($self:expr, $state:ident, $block:block, $id:ident) => {
@@ -60,13 +69,297 @@ macro_rules! key_style_change {
     }
 }
 
-pub static KEYBOARDS_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
-    confy::get_configuration_file_path("NuhxBoard", None)
+/// Directory holding the settings and keyboards, if overridden from the command line. Must be set
+/// before anything is loaded.
+pub static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
+/// Name of the settings file to use, without its extension, if overridden from the command line.
+pub static SETTINGS_PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Environment variable that overrides the directory holding the settings and keyboards.
+pub const HOME_VAR: &str = "NUHXBOARD_HOME";
+/// File that enables portable mode when it's next to the executable.
+pub const PORTABLE_MARKER: &str = "nuhxboard.portable";
+
+/// Returns the directory next to the executable that holds everything in portable mode, or `None`
+/// if portable mode isn't enabled.
+fn portable_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+    exe_dir
+        .join(PORTABLE_MARKER)
+        .exists()
+        .then(|| exe_dir.join("nuhxboard-data"))
+}
+
+/// Returns the directory holding the settings and keyboards. In order of precedence, this is the
+/// one given on the command line, the one in [`HOME_VAR`], the portable one, or the platform's
+/// config directory.
+pub fn config_dir() -> &'static Path {
+    CONFIG_DIR.get_or_init(|| {
+        if let Some(home) = std::env::var_os(HOME_VAR).filter(|home| !home.is_empty()) {
+            return PathBuf::from(home);
+        }
+        if let Some(dir) = portable_dir() {
+            return dir;
+        }
+        confy::get_configuration_file_path("NuhxBoard", None)
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_owned()
+    })
+}
+
+/// Returns the path of the settings file.
+pub fn settings_path() -> PathBuf {
+    let profile = SETTINGS_PROFILE.get().map(String::as_str);
+    let file = confy::get_configuration_file_path("NuhxBoard", profile).unwrap();
+    config_dir().join(file.file_name().unwrap())
+}
+
+/// Directory for files derived from the keyboards, which can be deleted at any time. This is the
+/// platform's cache directory, or one in the portable directory in portable mode.
+pub static CACHE_PATH: LazyLock<PathBuf> = LazyLock::new(|| match portable_dir() {
+    Some(dir) => dir.join("cache"),
+    None => etcetera::choose_base_strategy()
+        .map(|strategy| strategy.cache_dir())
+        .unwrap_or_else(|_| std::env::temp_dir())
+        .join("nuhxboard"),
+});
+
+/// Returns the path of the keyboard background image, resized to fit the window. Each config
+/// directory and settings profile has its own, so that instances don't overwrite each other's.
+pub fn background_path() -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    config_dir().hash(&mut hasher);
+    let dir = hasher.finish();
+    match SETTINGS_PROFILE.get() {
+        Some(profile) => CACHE_PATH.join(format!("background-{dir:016x}-{profile}.png")),
+        None => CACHE_PATH.join(format!("background-{dir:016x}.png")),
+    }
+}
+
+pub static KEYBOARDS_PATH: LazyLock<PathBuf> = LazyLock::new(|| config_dir().join("keyboards"));
+
+/// Returns the names of the keyboard categories, sorted.
+fn read_categories() -> Vec<String> {
+    let mut categories: Vec<_> = fs::read_dir(&*KEYBOARDS_PATH)
         .unwrap()
-        .parent()
+        .filter_map(|r| {
+            let entry = r.unwrap();
+            if entry.file_type().unwrap().is_dir() && entry.file_name() != "global" {
+                Some(entry.file_name().to_str().unwrap().to_owned())
+            } else {
+                None
+            }
+        })
+        .collect();
+    categories.sort();
+    categories
+}
+
+/// Returns the names of the keyboards in `category`, sorted.
+fn read_layouts(category: &str) -> Vec<String> {
+    let mut layouts: Vec<_> = fs::read_dir(KEYBOARDS_PATH.join(category))
         .unwrap()
-        .join("keyboards")
-});
+        .map(|r| r.unwrap())
+        .filter_map(|entry| {
+            if entry.file_type().unwrap().is_dir() && entry.file_name() != "images" {
+                Some(entry.file_name().to_str().unwrap().to_owned())
+            } else {
+                None
+            }
+        })
+        .collect();
+    layouts.sort();
+    layouts
+}
+
+/// Returns the editor message for a key press, if it's a shortcut.
+fn shortcut(key: iced::keyboard::Key, modifiers: iced::keyboard::Modifiers) -> Option<Message> {
+    use iced::keyboard::{Key, key::Named};
+    let command = (std::env::consts::OS == "macos" && modifiers.command()) || modifiers.control();
+    let step = if modifiers.shift() { 10.0 } else { 1.0 };
+    match key.as_ref() {
+        Key::Named(Named::ArrowLeft) => Some(Message::Nudge(Coord { x: -step, y: 0.0 })),
+        Key::Named(Named::ArrowRight) => Some(Message::Nudge(Coord { x: step, y: 0.0 })),
+        Key::Named(Named::ArrowUp) => Some(Message::Nudge(Coord { x: 0.0, y: -step })),
+        Key::Named(Named::ArrowDown) => Some(Message::Nudge(Coord { x: 0.0, y: step })),
+        Key::Named(Named::Delete) => Some(Message::RemoveElement),
+        Key::Named(Named::Escape) => Some(Message::UpdateSelection(BTreeSet::new())),
+        Key::Named(Named::Tab) => Some(Message::CycleSelection {
+            backwards: modifiers.shift(),
+        }),
+        Key::Character(character) if command => match character.to_lowercase().as_str() {
+            "z" if modifiers.shift() => Some(Message::Redo),
+            "z" => Some(Message::Undo),
+            "c" => Some(Message::Copy),
+            "x" => Some(Message::Cut),
+            "v" => Some(Message::Paste(None)),
+            "d" => Some(Message::Duplicate),
+            "s" => Some(Message::Save),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Finds the keyboard and style that recovered edits were made to, as indices into the category's
+/// keyboards and the keyboard's styles. Returns the item that no longer exists if one is missing.
+fn find_recovered_keyboard(
+    snapshot: &Snapshot,
+) -> Result<Option<(usize, Option<usize>)>, KeyboardItem> {
+    let category = KeyboardItem::Category(snapshot.category.clone());
+    if !snapshot.category.is_empty() && !category.path().is_dir() {
+        return Err(category);
+    }
+    let Some(name) = &snapshot.layout_name else {
+        return Ok(None);
+    };
+    let Some(layout) = read_layouts(&snapshot.category)
+        .iter()
+        .position(|l| l == name)
+    else {
+        return Err(KeyboardItem::Keyboard {
+            category: snapshot.category.clone(),
+            name: name.clone(),
+        });
+    };
+    let Some(choice) = &snapshot.style_choice else {
+        return Ok(Some((layout, None)));
+    };
+    match read_styles(&snapshot.category, name)
+        .iter()
+        .position(|s| s == choice)
+    {
+        Some(style) => Ok(Some((layout, Some(style)))),
+        None => Err(KeyboardItem::Style {
+            category: snapshot.category.clone(),
+            keyboard: name.clone(),
+            name: choice.name(),
+            global: choice.is_global(),
+        }),
+    }
+}
+
+/// Returns the styles available to the given keyboard: the default style, then the keyboard's own
+/// styles, then the global ones.
+fn read_styles(category: &str, layout: &str) -> Vec<StyleChoice> {
+    let style_names = |dir: PathBuf| -> Vec<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|r| {
+                let entry = r.unwrap();
+                if entry.file_type().unwrap().is_file()
+                    && entry.path().extension() == Some(std::ffi::OsStr::new("style"))
+                {
+                    Some(
+                        entry
+                            .path()
+                            .file_stem()
+                            .unwrap()
+                            .to_str()
+                            .unwrap()
+                            .to_owned(),
+                    )
+                } else {
+                    None
+                }
+            })
+            .collect()
+    };
+
+    let mut styles = vec![StyleChoice::Default];
+    styles.extend(
+        style_names(KEYBOARDS_PATH.join(category).join(layout))
+            .into_iter()
+            .map(StyleChoice::Custom),
+    );
+    styles.extend(
+        style_names(KEYBOARDS_PATH.join("global"))
+            .into_iter()
+            .map(StyleChoice::Global),
+    );
+    styles
+}
+
+/// Converts the layout and style indices saved by older versions to names.
+fn migrate_indices(settings: &mut Settings) {
+    let style_index = settings.style_index.take();
+    let Some(layout_index) = settings.layout_index.take() else {
+        return;
+    };
+    if settings.category.is_empty() || !KEYBOARDS_PATH.join(&settings.category).is_dir() {
+        return;
+    }
+    info!(
+        layout_index,
+        ?style_index,
+        "Migrating saved layout and style indices"
+    );
+    settings.layout = read_layouts(&settings.category).get(layout_index).cloned();
+    settings.style = settings
+        .layout
+        .as_ref()
+        .zip(style_index)
+        .and_then(|(layout, index)| {
+            read_styles(&settings.category, layout)
+                .get(index)
+                .and_then(StyleChoice::setting)
+        });
+}
+
+/// Writes `contents` to `path` by way of a temporary file, so that a failed write never leaves a
+/// truncated file behind. Up to `backups` previous versions are kept next to it, from
+/// `{path}.1.bak` (the newest) to `{path}.{backups}.bak`.
+pub fn write_file(path: &Path, contents: &[u8], backups: u32) -> std::io::Result<()> {
+    // Paths typed in by the user can be a root or end in `..`
+    let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} isn't a path to a file", path.display()),
+        ));
+    };
+    let file_name = file_name.to_string_lossy();
+    fs::create_dir_all(parent)?;
+
+    let temp = parent.join(format!(".{file_name}.tmp"));
+    let write_temp = || {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()
+    };
+    if let Err(e) = write_temp() {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    if backups > 0 && path.exists() {
+        let backup = |n: u32| parent.join(format!("{file_name}.{n}.bak"));
+        for n in (1..backups).rev() {
+            if backup(n).exists() {
+                fs::rename(backup(n), backup(n + 1))?;
+            }
+        }
+        fs::copy(path, backup(1))?;
+    }
+    fs::rename(&temp, path)
+}
+
+/// Recursively copies the directory `from` to `to`, which must not exist yet.
+pub fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
 
 // TODO: selectively clear fg or bg depending on the context
 #[derive(Default)]
@@ -122,23 +415,47 @@ pub struct NuhxBoard {
     pub edit_mode: bool,
     pub edit_history: Vec<Change>,
     pub history_depth: usize,
+    /// What the last change in the edit history was made to, if it can be extended by further edits.
+    pub last_edit: Option<EditTarget>,
+    /// Whether a recovery snapshot was staged since one was last written.
+    pub recovery_pending: bool,
     pub save_keyboard_as_category: String,
     pub save_layout_as_name: String,
     pub save_style_as_name: String,
     pub save_style_as_global: bool,
+    pub save_layout_as_nohboard: bool,
     pub color_pickers: ColorPickers,
     pub text_input: TextInput,
     pub number_input: NumberInput,
     pub selections: SelectionLists,
     pub hovered_element: Option<usize>,
+    pub selected_elements: BTreeSet<usize>,
     pub detecting: Vec<usize>,
+    pub learning: Option<Learning>,
+    /// Where the context menu was opened, in layout coordinates.
     pub right_click_pos: iced::Point,
     pub mouse_pos: iced::Point,
+    /// Zoom of the edit canvas. This only affects how the layout is displayed.
+    pub zoom: f32,
+    /// Offset of the edit canvas, in window coordinates.
+    pub pan: iced::Vector,
     pub layout_commited: bool,
     pub style_commited: bool,
+    /// Unsaved edits left behind by a previous run, until they're restored or discarded.
+    pub recovered: Option<Snapshot>,
+    /// The keyboard selected in the settings file, and the one loaded in its place from the
+    /// command line. The former is what gets saved, unless another keyboard is loaded from the UI.
+    pub launch_override: Option<(Selection, Selection)>,
 }
 
-const DEFAULT_KEY_SIZE: f32 = 43.0;
+/// A keyboard category, layout and style, as they're stored in the settings.
+pub type Selection = (String, Option<String>, Option<String>);
+
+/// A window that can be reopened on startup, and the id of the element it refers to by index, if
+/// it does.
+type RestorableWindow = (Box<dyn Window<NuhxBoard, Theme, Message>>, Option<u32>);
+
+pub const DEFAULT_KEY_SIZE: f32 = 43.0;
 
 pub const DEFAULT_WINDOW_SIZE: iced::Size = iced::Size {
     width: 200.0,
@@ -146,16 +463,17 @@ pub const DEFAULT_WINDOW_SIZE: iced::Size = iced::Size {
 };
 
 impl NuhxBoard {
-    pub fn new() -> (Self, Task<Message>) {
+    pub fn new(launch: Launch) -> (Self, Task<Message>) {
         let span = info_span!("startup");
         let _guard = span.enter();
         let mut settings_error = None;
 
         info!("Loading settings");
-        let settings: Settings = confy::load("NuhxBoard", None).unwrap_or_else(|e| {
+        let mut settings: Settings = confy::load_path(settings_path()).unwrap_or_else(|e| {
             settings_error = Some(NuhxBoardError::SettingsParse(Arc::new(e)));
             Settings::default()
         });
+        migrate_indices(&mut settings);
 
         let layout = Layout {
             version: None,
@@ -164,9 +482,31 @@ impl NuhxBoard {
             elements: Vec::new(),
         };
 
-        let category = settings.category.clone();
-        let keyboard = settings.layout_index;
-        let style = settings.style;
+        // What's given on the command line replaces what's saved. The saved layout and style are
+        // only kept if they belong to the category and layout being loaded.
+        let overridden =
+            launch.category.is_some() || launch.layout.is_some() || launch.style.is_some();
+        let saved_selection = (
+            settings.category.clone(),
+            settings.layout.clone(),
+            settings.style.clone(),
+        );
+        let same_category = launch
+            .category
+            .as_ref()
+            .is_none_or(|category| *category == settings.category);
+        let same_layout = same_category
+            && launch
+                .layout
+                .as_ref()
+                .is_none_or(|layout| Some(layout) == settings.layout.as_ref());
+        let category = launch.category.unwrap_or_else(|| settings.category.clone());
+        let keyboard = launch
+            .layout
+            .or_else(|| same_category.then(|| settings.layout.clone()).flatten());
+        let style = launch
+            .style
+            .or_else(|| same_layout.then(|| settings.style.clone()).flatten());
 
         let caps = match settings.capitalization {
             Capitalization::Upper => true,
@@ -179,7 +519,15 @@ impl NuhxBoard {
         // The app will open the main window on startup. The WindowManager automatically tracks IDs
         // and corresponding window types and runs the correct view, theme, and title logic when
         // necessary.
-        let (main_window, window_open_task) = windows.open(Box::new(Main));
+        let main_position = settings
+            .open_windows
+            .iter()
+            .find(|window| window.id == Main.id())
+            .and_then(|window| window.position.clone());
+        let (main_window, window_open_task) = match main_position {
+            Some(position) => windows.open_at(Box::new(Main), position.into()),
+            None => windows.open(Box::new(Main)),
+        };
 
         let mut app = Self {
             windows,
@@ -200,8 +548,8 @@ impl NuhxBoard {
             pressed_scroll_buttons: HashMap::new(),
             previous_mouse_position: Coord::zero(),
             previous_mouse_time: std::time::SystemTime::now(),
-            layout_choice: Some(settings.layout_index),
-            style_choice: settings.style,
+            layout_choice: None,
+            style_choice: 0,
             layout_options: Vec::new(),
             keyboard_category_options: Vec::new(),
             style_options: Vec::new(),
@@ -211,30 +559,50 @@ impl NuhxBoard {
             edit_mode: false,
             edit_history: Vec::new(),
             history_depth: 0,
+            last_edit: None,
+            recovery_pending: false,
             save_keyboard_as_category: String::new(),
             save_layout_as_name: String::new(),
             save_style_as_name: String::new(),
             save_style_as_global: false,
+            save_layout_as_nohboard: false,
             color_pickers: ColorPickers::default(),
             text_input: TextInput::default(),
             hovered_element: None,
+            selected_elements: BTreeSet::new(),
             number_input: NumberInput::default(),
             selections: SelectionLists::default(),
             detecting: Vec::new(),
+            learning: None,
             right_click_pos: iced::Point::default(),
             mouse_pos: iced::Point::default(),
+            zoom: 1.0,
+            pan: iced::Vector::ZERO,
             layout_commited: true,
             style_commited: true,
+            recovered: recovery::load(),
+            launch_override: None,
         };
 
-        let mut tasks = Vec::with_capacity(5);
+        let mut tasks = Vec::with_capacity(6);
         tasks.push(window_open_task.map(|_| Message::None));
         if !category.is_empty() {
-            tasks.extend([
-                app.update(Message::ChangeKeyboardCategory(category)),
-                app.update(Message::LoadLayout(keyboard)),
-                app.update(Message::LoadStyle(style)),
-            ]);
+            tasks.push(app.load_saved_keyboard(category, keyboard, style));
+        }
+        if overridden {
+            app.launch_override = Some((saved_selection, app.selection()));
+        }
+        if launch.edit || app.settings.edit_mode {
+            app.edit_mode = true;
+        }
+        tasks.push(app.restore_windows());
+        if app.recovered.is_some() {
+            tasks.push(
+                app.windows
+                    .open(Box::new(RecoveryPopup))
+                    .1
+                    .map(|_| Message::None),
+            );
         }
         if let Some(error) = settings_error {
             tasks.push(app.error(error));
@@ -277,29 +645,167 @@ impl NuhxBoard {
 
                 if !self.startup {
                     self.layout_choice = None;
-                    self.settings.layout_index = 0;
+                    self.settings.layout = None;
                     self.style_choice = 0;
-                    self.settings.style = 0;
+                    self.settings.style = None;
                     self.style_options = Vec::new();
                 }
 
-                self.layout_options = fs::read_dir(KEYBOARDS_PATH.join(&self.settings.category))
-                    .unwrap()
-                    .map(|r| r.unwrap())
-                    .filter_map(|entry| {
-                        if entry.file_type().unwrap().is_dir() && entry.file_name() != "images" {
-                            Some(entry.file_name().to_str().unwrap().to_owned())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                self.layout_options.sort();
+                self.layout_options = read_layouts(&self.settings.category);
             }
             Message::LoadLayout(layout) => {
                 info!(layout, "Layout changed");
                 return self.load_layout(layout);
             }
+            Message::CreateKeyboard {
+                category,
+                name,
+                options,
+                size,
+            } => {
+                info!(category, name, ?options, "Creating keyboard");
+                let item = KeyboardItem::Keyboard {
+                    category: category.clone(),
+                    name: name.clone(),
+                };
+                let mut layout = match generator::generate(&options) {
+                    Ok(layout) => layout,
+                    Err(e) => return self.error(NuhxBoardError::Generate(e)),
+                };
+                layout.width = size.width;
+                layout.height = size.height;
+                let result = fs::create_dir_all(KEYBOARDS_PATH.join(&category))
+                    .and_then(|_| fs::create_dir(item.path()))
+                    .and_then(|_| Ok(serde_json::to_vec_pretty(&layout)?))
+                    .and_then(|contents| {
+                        write_file(&item.path().join("keyboard.json"), &contents, 0)
+                    });
+                if let Err(e) = result {
+                    return self.error(NuhxBoardError::KeyboardFiles(Arc::new(e)));
+                }
+
+                let close = self
+                    .windows
+                    .close_all_of(Box::new(NewKeyboard))
+                    .map(|_| Message::None);
+                if !self.layout_commited || !self.style_commited {
+                    // The new keyboard is listed, but loading it is left until the unsaved
+                    // changes are saved or discarded
+                    let layout = self.layout_choice.map(|i| self.layout_options[i].clone());
+                    let style = self.style_options.get(self.style_choice).cloned();
+                    let refresh = self.refresh_keyboard_options(
+                        self.settings.category.clone(),
+                        layout,
+                        style,
+                    );
+                    let (_, unsaved) = self
+                        .windows
+                        .open(Box::new(UnsavedChangesPopup(Action::LoadKeyboard)));
+                    return Task::batch([close, refresh, unsaved.map(|_| Message::None)]);
+                }
+                let refresh = self.refresh_keyboard_options(category, None, None);
+                let index = self.layout_options.iter().position(|l| *l == name).unwrap();
+                return Task::batch([refresh, self.load_layout(index), close]);
+            }
+            Message::RenameItem { item, name } => {
+                info!(%item, name, "Renaming keyboard item");
+                let renamed = item.with_name(name);
+                let target = renamed.path();
+                let result = if target.exists() {
+                    Err(std::io::Error::new(
+                        std::io::ErrorKind::AlreadyExists,
+                        format!("A {renamed} already exists"),
+                    ))
+                } else {
+                    fs::rename(item.path(), target)
+                };
+                let dialog = NameItem {
+                    item: item.clone(),
+                    duplicate: false,
+                };
+                return self.finish_item_change(result, &item, Some(&renamed), Box::new(dialog));
+            }
+            Message::DuplicateItem { item, name } => {
+                info!(%item, name, "Duplicating keyboard item");
+                let copy = item.with_name(name);
+                let result = match item {
+                    KeyboardItem::Style { .. } if copy.path().exists() => Err(std::io::Error::new(
+                        std::io::ErrorKind::AlreadyExists,
+                        format!("A {copy} already exists"),
+                    )),
+                    KeyboardItem::Style { .. } => fs::copy(item.path(), copy.path()).map(|_| ()),
+                    _ => copy_dir(&item.path(), &copy.path()),
+                };
+                let dialog = NameItem {
+                    item: item.clone(),
+                    duplicate: true,
+                };
+                // The original stays selected
+                return self.finish_item_change(result, &item, Some(&item), Box::new(dialog));
+            }
+            Message::DeleteItem(item) => {
+                info!(%item, "Deleting keyboard item");
+                let result = match item {
+                    KeyboardItem::Style { .. } => fs::remove_file(item.path()),
+                    _ => fs::remove_dir_all(item.path()),
+                };
+                let dialog = ConfirmDelete(item.clone());
+                return self.finish_item_change(result, &item, None, Box::new(dialog));
+            }
+            Message::ExportPack { item, path } => {
+                info!(%item, ?path, "Exporting keyboard pack");
+                let (category, keyboards) = match &item {
+                    KeyboardItem::Category(category) => (category.clone(), read_layouts(category)),
+                    KeyboardItem::Keyboard { category, name } => {
+                        (category.clone(), vec![name.clone()])
+                    }
+                    KeyboardItem::Style { .. } => return Task::none(),
+                };
+                let mut global_styles =
+                    match archive::category_global_styles(&KEYBOARDS_PATH, &category) {
+                        Ok(styles) => styles,
+                        Err(e) => return self.error(NuhxBoardError::PackExport(Arc::new(e))),
+                    };
+                // The global style loaded with an exported keyboard is used by it, whether or not
+                // it has images
+                if let (Some(index), Some(StyleChoice::Global(name))) = (
+                    self.layout_choice,
+                    self.style_options.get(self.style_choice),
+                ) && self.settings.category == category
+                    && keyboards.contains(&self.layout_options[index])
+                    && !global_styles.contains(name)
+                {
+                    global_styles.push(name.clone());
+                }
+                if let Err(e) = archive::export_pack(
+                    &KEYBOARDS_PATH,
+                    &category,
+                    &keyboards,
+                    &global_styles,
+                    &path,
+                ) {
+                    return self.error(NuhxBoardError::PackExport(Arc::new(e)));
+                }
+                return self
+                    .windows
+                    .close_all_of(Box::new(ExportPack(item)))
+                    .map(|_| Message::None);
+            }
+            Message::ImportPack { path, conflicts } => {
+                info!(?path, ?conflicts, "Importing keyboard pack");
+                if let Err(e) = archive::import_pack(&KEYBOARDS_PATH, &path, conflicts) {
+                    return self.error(NuhxBoardError::PackImport(Arc::new(e)));
+                }
+                let close = self
+                    .windows
+                    .close_all_of(Box::new(ImportPack))
+                    .map(|_| Message::None);
+                let layout = self.layout_choice.map(|i| self.layout_options[i].clone());
+                let style = self.style_options.get(self.style_choice).cloned();
+                let refresh =
+                    self.refresh_keyboard_options(self.settings.category.clone(), layout, style);
+                return Task::batch([close, refresh]);
+            }
             Message::LoadStyle(style) => {
                 info!(style, "Style changed");
                 return self.load_style(style);
@@ -350,6 +856,18 @@ impl NuhxBoard {
                     Setting::UpdateTextPosition => {
                         self.settings.update_text_position = !self.settings.update_text_position;
                     }
+                    Setting::SnapToGrid => {
+                        self.settings.snapping.grid = !self.settings.snapping.grid;
+                    }
+                    Setting::GridSubdivisions(subdivisions) => {
+                        self.settings.snapping.subdivisions = subdivisions;
+                    }
+                    Setting::SnapToElements => {
+                        self.settings.snapping.elements = !self.settings.snapping.elements;
+                    }
+                    Setting::Backups(backups) => {
+                        self.settings.backups = backups;
+                    }
                 }
             }
             Message::ClearPressedKeys => {
@@ -364,11 +882,18 @@ impl NuhxBoard {
                     info!("Entering edit mode");
                 }
                 self.edit_mode = !self.edit_mode;
-            }
-            Message::MoveElement { index, delta } => {
-                debug!(delta = ?(delta.x, delta.y), index, "Moving element");
-                self.layout.elements[index].translate(delta, self.settings.update_text_position);
-                self.caches[index].clear();
+                self.update_selection(BTreeSet::new());
+                self.set_learning(None);
+                self.zoom = 1.0;
+                self.pan = iced::Vector::ZERO;
+            }
+            Message::MoveElements { indices, delta } => {
+                debug!(delta = ?(delta.x, delta.y), ?indices, "Moving elements");
+                for index in indices {
+                    self.layout.elements[index]
+                        .translate(delta, self.settings.update_text_position);
+                    self.caches[index].clear();
+                }
             }
             Message::MoveFace { index, face, delta } => {
                 debug!(index, face, delta = ?(delta.x, delta.y), "Moving face");
@@ -394,78 +919,99 @@ impl NuhxBoard {
             }
             Message::SaveLayout(file) => {
                 info!(?file, "Saving layout");
-                let path = file.unwrap_or(KEYBOARDS_PATH.join(format!(
-                    "{}/{}/keyboard.json",
-                    self.settings.category,
-                    self.layout_options[self.layout_choice.unwrap()]
-                )));
-                fs::create_dir_all(path.parent().unwrap()).unwrap();
-                let mut file = File::create(path).unwrap();
-                serde_json::to_writer_pretty(&mut file, &self.layout).unwrap();
+                let path = match (file, self.layout_choice) {
+                    (Some(path), _) => path,
+                    (None, Some(index)) => KeyboardItem::Keyboard {
+                        category: self.settings.category.clone(),
+                        name: self.layout_options[index].clone(),
+                    }
+                    .path()
+                    .join("keyboard.json"),
+                    (None, None) => return self.error(NuhxBoardError::NoSaveTarget("keyboard")),
+                };
+                let result = serde_json::to_vec_pretty(&self.layout)
+                    .map_err(Into::into)
+                    .and_then(|contents| write_file(&path, &contents, self.settings.backups));
+                if let Err(e) = result {
+                    return self.error(NuhxBoardError::Save(path, Arc::new(e)));
+                }
                 self.layout_commited = true;
+                if self.style_commited {
+                    recovery::clear();
+                }
             }
             Message::SaveStyle(file) => {
                 info!(?file, "Saving style");
-                let path = file.unwrap_or(KEYBOARDS_PATH.join(format!(
-                    "{}/{}/{}.style",
-                    self.settings.category,
-                    self.layout_options[self.layout_choice.unwrap()],
-                    self.style_options[self.style_choice]
-                )));
-                let mut file = File::create(path).unwrap();
-                serde_json::to_writer_pretty(&mut file, &self.style).unwrap();
+                let style = self.style_options.get(self.style_choice);
+                let path = match (file, self.layout_choice, style) {
+                    (Some(path), _, _) => path,
+                    (None, Some(index), Some(style)) => KeyboardItem::Style {
+                        category: self.settings.category.clone(),
+                        keyboard: self.layout_options[index].clone(),
+                        name: style.name(),
+                        global: style.is_global(),
+                    }
+                    .path(),
+                    _ => return self.error(NuhxBoardError::NoSaveTarget("style")),
+                };
+                let result = serde_json::to_vec_pretty(&self.style)
+                    .map_err(Into::into)
+                    .and_then(|contents| write_file(&path, &contents, self.settings.backups));
+                if let Err(e) = result {
+                    return self.error(NuhxBoardError::Save(path, Arc::new(e)));
+                }
                 self.style_commited = true;
+                if self.layout_commited {
+                    recovery::clear();
+                }
             }
             Message::SetHeight(height) => {
                 debug!(height, "Setting height");
-                self.layout.height = height;
-                return window::resize(
-                    self.main_window,
-                    iced::Size {
-                        width: self.layout.width,
-                        height: self.layout.height,
-                    },
-                );
+                let before = iced::Size {
+                    width: self.layout.width,
+                    height: self.layout.height,
+                };
+                let after = iced::Size { height, ..before };
+                return self.apply_new_change(Change::Resize { before, after });
             }
             Message::SetWidth(width) => {
                 debug!(width, "Setting width");
-                self.layout.width = width;
-                return window::resize(
-                    self.main_window,
-                    iced::Size {
-                        width: self.layout.width,
-                        height: self.layout.height,
-                    },
-                );
+                let before = iced::Size {
+                    width: self.layout.width,
+                    height: self.layout.height,
+                };
+                let after = iced::Size { width, ..before };
+                return self.apply_new_change(Change::Resize { before, after });
             }
             Message::PushChange(change) => {
                 debug!(?change, "Pushing change");
-                if self.history_depth > 0 {
-                    self.edit_history
-                        .truncate(self.edit_history.len() - self.history_depth);
-                    self.history_depth = 0;
-                }
-                self.edit_history.push(change);
+                self.push_change(change);
                 self.layout_commited = false;
             }
             Message::Undo => {
                 debug!("Undo");
                 if self.history_depth < self.edit_history.len() {
                     self.history_depth += 1;
-                    self.apply_change(
+                    self.last_edit = None;
+                    let task = self.apply_change(
                         self.edit_history[self.edit_history.len() - self.history_depth].clone(),
                         true,
                     );
+                    self.stage_recovery();
+                    return task;
                 }
             }
             Message::Redo => {
                 debug!("Redo");
                 if self.history_depth > 0 {
                     self.history_depth -= 1;
-                    self.apply_change(
+                    self.last_edit = None;
+                    let task = self.apply_change(
                         self.edit_history[self.edit_history.len() - self.history_depth - 1].clone(),
                         false,
                     );
+                    self.stage_recovery();
+                    return task;
                 }
             }
             Message::ChangeTextInput(input, value) => {
@@ -509,7 +1055,10 @@ impl NuhxBoard {
             }
             Message::ChangeStyle(style) => {
                 debug!(?style, "Changing style");
+                let before = Box::new(self.style.clone());
+                let target = EditTarget::style(&style);
                 self.change_style(style);
+                self.push_style_change(before, target);
             }
             Message::ToggleSaveStyleAsGlobal => {
                 debug!(
@@ -518,71 +1067,82 @@ impl NuhxBoard {
                 );
                 self.save_style_as_global = !self.save_style_as_global;
             }
+            Message::ToggleSaveLayoutAsNohBoard => {
+                debug!(
+                    save_layout_as_nohboard = !self.save_layout_as_nohboard,
+                    "Toggling save layout as NohBoard compatible"
+                );
+                self.save_layout_as_nohboard = !self.save_layout_as_nohboard;
+            }
+            Message::ExportLayout(path) => {
+                info!(?path, "Exporting layout");
+                let mut layout = self.layout.clone();
+                layout.tessellate();
+                let result = serde_json::to_vec_pretty(&layout)
+                    .map_err(Into::into)
+                    .and_then(|contents| write_file(&path, &contents, self.settings.backups));
+                if let Err(e) = result {
+                    return self.error(NuhxBoardError::Save(path, Arc::new(e)));
+                }
+            }
             Message::Open(window) => {
                 info!(id = window.id(), "Opening new window");
                 if window == LoadKeyboard {
-                    if (!self.layout_commited || !self.style_commited)
-                        && !self
-                            .windows
-                            .any_of(&UnsavedChangesPopup(Action::LoadKeyboard))
-                    {
+                    if !self.layout_commited || !self.style_commited {
                         return self
                             .windows
                             .open(Box::new(UnsavedChangesPopup(Action::LoadKeyboard)))
                             .1
                             .map(|_| Message::None);
                     }
-                    self.keyboard_category_options = fs::read_dir(&*KEYBOARDS_PATH)
-                        .unwrap()
-                        .filter_map(|r| {
-                            let entry = r.unwrap();
-                            if entry.file_type().unwrap().is_dir() && entry.file_name() != "global"
-                            {
-                                Some(entry.file_name().to_str().unwrap().to_owned())
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-                    self.keyboard_category_options.sort();
+                    self.keyboard_category_options = read_categories();
                 } else if window == SaveStyleAs {
-                    self.save_style_as_global = self.style_options[self.style_choice].is_global();
+                    self.save_style_as_global = self
+                        .style_options
+                        .get(self.style_choice)
+                        .is_some_and(StyleChoice::is_global);
                 }
                 return self.windows.open(window).1.map(|_| Message::None);
             }
-            Message::CloseAllOf(window) => {
-                info!(id = window.id(), "Closing all windows");
-                return self.windows.close_all_of(window).map(|_| Message::None);
-            }
             Message::Exit => {
                 info!("Exiting");
                 return immediate_task(Message::CloseRequested);
             }
             Message::Closed(window) => {
                 info!(%window, "Window closed");
-                self.windows.was_closed(window);
+                let close_children = self.windows.was_closed(window);
 
                 if self.windows.empty() {
                     return iced::exit();
                 }
+                return close_children.map(|_| Message::None);
             }
             Message::CloseRequested => {
-                if (!self.layout_commited || !self.style_commited)
-                    && !self.windows.any_of(&UnsavedChangesPopup(Action::Exit))
-                {
+                if !self.layout_commited || !self.style_commited {
                     return self
                         .windows
                         .open(Box::new(UnsavedChangesPopup(Action::Exit)))
                         .1
                         .map(|_| Message::None);
                 }
-                confy::store("nuhxboard", None, self.settings.clone()).unwrap();
+                self.settings.open_windows = self.open_windows();
+                self.settings.edit_mode = self.edit_mode;
+                let mut settings = self.settings.clone();
+                // The keyboard given on the command line is only remembered if it was changed from
+                // the UI
+                if let Some((saved, launched)) = &self.launch_override
+                    && *launched == self.selection()
+                {
+                    (settings.category, settings.layout, settings.style) = saved.clone();
+                }
+                confy::store_path(settings_path(), settings).unwrap();
                 if !self.windows.empty() {
                     return self.windows.close_all().map(|_| Message::None);
                 }
             }
             Message::ChangeColor(picker, color) => {
                 debug!(?picker, ?color, "Changing color picker");
+                let before = Box::new(self.style.clone());
                 // I love macros!
                 macro_rules! mouse_speed_indicator_style_change {
                     ($name:ident, $block:block, $id:ident) => {
@@ -682,6 +1242,7 @@ impl NuhxBoard {
                         );
                     }
                 }
+                self.push_style_change(before, Some(EditTarget::Color(picker)));
                 self.style_commited = false;
             }
             Message::ToggleColorPicker(picker) => {
@@ -698,8 +1259,245 @@ impl NuhxBoard {
                 }
                 self.hovered_element = hovered_element;
             }
+            Message::UpdateSelection(selection) => {
+                debug!(?selection, "Updating selection");
+                self.update_selection(selection);
+            }
+            Message::Align(align) => {
+                debug!(?align, "Aligning elements");
+                return self.align(align);
+            }
+            Message::Copy => {
+                debug!("Copying elements");
+                if let Some(contents) = self.copy_elements() {
+                    return iced::clipboard::write(contents);
+                }
+            }
+            Message::Cut => {
+                debug!("Cutting elements");
+                let Some(contents) = self.copy_elements() else {
+                    return Task::none();
+                };
+                return Task::batch([iced::clipboard::write(contents), self.remove_elements()]);
+            }
+            Message::Paste(position) => {
+                if !self.edit_mode {
+                    return Task::none();
+                }
+                let position = position.unwrap_or(self.canvas_point(self.mouse_pos));
+                return iced::clipboard::read()
+                    .map(move |contents| Message::PasteElements { contents, position });
+            }
+            Message::PasteElements { contents, position } => {
+                debug!(?position, "Pasting elements");
+                let Some(clipboard) = contents
+                    .and_then(|contents| serde_json::from_str::<ClipboardElements>(&contents).ok())
+                else {
+                    info!("Clipboard doesn't contain elements");
+                    return Task::none();
+                };
+                let Some(bounds) = group_bounding_rect(&clipboard.elements) else {
+                    return Task::none();
+                };
+                let delta = Coord {
+                    x: position.x,
+                    y: position.y,
+                } - bounds.center();
+                return self.add_elements(clipboard, delta);
+            }
+            Message::Nudge(delta) => {
+                let indices = self.target_elements();
+                if !self.edit_mode || indices.is_empty() {
+                    return Task::none();
+                }
+                debug!(delta = ?(delta.x, delta.y), ?indices, "Nudging elements");
+                return self.apply_new_change(Change::MoveElements { indices, delta });
+            }
+            Message::CycleSelection { backwards } => {
+                let len = self.layout.elements.len();
+                if !self.edit_mode || len == 0 {
+                    return Task::none();
+                }
+                let current = self.selected_elements.first().copied();
+                let next = match (current, backwards) {
+                    (Some(index), false) => (index + 1) % len,
+                    (Some(index), true) => (index + len - 1) % len,
+                    (None, false) => 0,
+                    (None, true) => len - 1,
+                };
+                self.update_selection(BTreeSet::from([next]));
+            }
+            Message::Shortcut(window, message) => {
+                if self.windows.is_blocked(window) || self.windows.is_blocked(self.main_window) {
+                    return Task::none();
+                }
+                return self.update(*message);
+            }
+            Message::Save => {
+                if !self.edit_mode || self.layout_choice.is_none() {
+                    return Task::none();
+                }
+                return Task::batch([
+                    immediate_task(Message::SaveLayout(None)),
+                    immediate_task(Message::SaveStyle(None)),
+                ]);
+            }
+            Message::InsertVertex {
+                index,
+                face,
+                position,
+            } => {
+                debug!(index, face, ?position, "Inserting vertex");
+                return self.apply_new_change(Change::InsertVertex {
+                    index,
+                    vertex: face + 1,
+                    position: position.into(),
+                });
+            }
+            Message::RemoveVertex { index, vertex } => {
+                let Ok(def) = CommonDefinitionRef::try_from(&self.layout.elements[index]) else {
+                    return Task::none();
+                };
+                // Rectangles and NohBoard layouts repeat the first point at the end to close the
+                // ring, which doesn't count as a vertex
+                let last = def.boundaries.len().saturating_sub(1);
+                let closed = last > 0 && def.boundaries[0] == def.boundaries[last];
+                // Anything less wouldn't be a polygon
+                if def.boundaries.len() - usize::from(closed) <= 3 {
+                    return Task::none();
+                }
+                debug!(index, vertex, closed, "Removing vertex");
+                if closed && (vertex == 0 || vertex == last) {
+                    // The ring is closed at the next point instead
+                    let before = Box::new(self.layout.elements[index].clone());
+                    let mut after = before.clone();
+                    let def = CommonDefinitionMut::try_from(&mut *after).unwrap();
+                    def.boundaries.remove(last);
+                    def.boundaries.remove(0);
+                    let first = def.boundaries[0].clone();
+                    def.boundaries.push(first);
+                    return self.apply_new_change(Change::EditElement {
+                        index,
+                        before,
+                        after,
+                    });
+                }
+                let position = def.boundaries[vertex].clone();
+                return self.apply_new_change(Change::RemoveVertex {
+                    index,
+                    vertex,
+                    position,
+                });
+            }
+            Message::StartLearning => {
+                let candidates: Vec<_> = if self.selected_elements.len() > 1 {
+                    self.selected_elements.iter().copied().collect()
+                } else {
+                    (0..self.layout.elements.len()).collect()
+                };
+                let order = self.reading_order(
+                    candidates
+                        .into_iter()
+                        .filter(|index| {
+                            matches!(self.layout.elements[*index], BoardElement::KeyboardKey(_))
+                        })
+                        .collect(),
+                );
+                info!(keys = order.len(), "Learning keycodes");
+                self.update_selection(BTreeSet::new());
+                self.set_learning(Some(Learning { order, position: 0 }));
+            }
+            Message::StopLearning => {
+                info!("Stopped learning keycodes");
+                self.set_learning(None);
+            }
+            Message::SkipLearning => {
+                debug!("Skipping key");
+                self.advance_learning();
+            }
+            Message::LearnElement(index) => {
+                let Some(mut learning) = self.learning.clone() else {
+                    return Task::none();
+                };
+                let Some(position) = learning.order.iter().position(|i| *i == index) else {
+                    return Task::none();
+                };
+                debug!(index, "Learning element");
+                learning.position = position;
+                self.set_learning(Some(learning));
+            }
+            Message::LabelKeys(locale) => {
+                info!(%locale, "Labelling keys");
+                return self.label_keys(None, locale);
+            }
+            Message::RelabelKeys { from, to } => {
+                info!(%from, %to, "Relabelling keys");
+                return self.label_keys(Some(from), to);
+            }
+            Message::CombineShapes(operation) => {
+                debug!(?operation, "Combining shapes");
+                return self.combine_shapes(operation);
+            }
+            Message::TransformElements { transform, pivot } => {
+                let indices = self.target_elements();
+                if !self.edit_mode {
+                    return Task::none();
+                }
+                let Some(bounds) =
+                    group_bounding_rect(indices.iter().map(|index| &self.layout.elements[*index]))
+                else {
+                    return Task::none();
+                };
+                let pivot = pivot.unwrap_or(bounds.center());
+                debug!(?transform, ?pivot, ?indices, "Transforming elements");
+                // The y axis points down, so geo's counterclockwise rotation appears clockwise
+                let affine = match transform {
+                    ElementTransform::Rotate(degrees) => AffineTransform::rotate(degrees, pivot),
+                    ElementTransform::Scale(factor) => {
+                        AffineTransform::scale(factor, factor, pivot)
+                    }
+                    ElementTransform::FlipHorizontally => AffineTransform::scale(-1.0, 1.0, pivot),
+                    ElementTransform::FlipVertically => AffineTransform::scale(1.0, -1.0, pivot),
+                };
+                let changes = indices
+                    .into_iter()
+                    .map(|index| {
+                        let before = self.layout.elements[index].clone();
+                        let mut after = before.clone();
+                        after.affine_transform(&affine);
+                        Change::EditElement {
+                            index,
+                            before: Box::new(before),
+                            after: Box::new(after),
+                        }
+                    })
+                    .collect();
+                return self.apply_new_change(Change::Group(changes));
+            }
+            Message::SetView { zoom, pan } => {
+                trace!(zoom, ?pan, "Changing view");
+                self.zoom = zoom;
+                self.pan = pan;
+            }
+            Message::Duplicate => {
+                debug!("Duplicating elements");
+                if !self.edit_mode {
+                    return Task::none();
+                }
+                let clipboard = self.clipboard_elements();
+                let offset = DEFAULT_KEY_SIZE / self.settings.snapping.subdivisions.max(1) as f32;
+                return self.add_elements(
+                    clipboard,
+                    Coord {
+                        x: offset,
+                        y: offset,
+                    },
+                );
+            }
             Message::ChangeElement(element_i, property) => {
                 debug!(element_i, ?property, "Changing element");
+                let before = Box::new(self.layout.elements[element_i].clone());
+                let target = EditTarget::element(element_i, &property);
                 let element = &mut self.layout.elements[element_i];
                 let mouse_key = matches!(
                     element,
@@ -711,6 +1509,8 @@ impl NuhxBoard {
                         ElementProperty::Text(ref v) => *def.text = v.clone(),
                         ElementProperty::TextPositionX(v) => *def.text_position.x = v,
                         ElementProperty::TextPositionY(v) => *def.text_position.y = v,
+                        ElementProperty::CornerRadius(v) => *def.corner_radius = v,
+                        ElementProperty::Shape(v) => *def.shape = v,
                         ElementProperty::Boundary(i, ref v) => {
                             if let Some(v) = v {
                                 if i >= def.boundaries.len() {
@@ -760,10 +1560,12 @@ impl NuhxBoard {
                     }
                 }
                 self.caches[element_i].clear();
+                self.push_element_change(element_i, before, target);
                 self.layout_commited = false;
             }
             Message::CenterTextPosition(i) => {
                 debug!(element = i, "Centering text position");
+                let before = Box::new(self.layout.elements[i].clone());
                 let element = &mut self.layout.elements[i];
                 let Ok(def) = CommonDefinitionMut::try_from(element) else {
                     panic!("Cannot center text position of mouse speed indicator");
@@ -782,6 +1584,7 @@ impl NuhxBoard {
                 def.text_position.x = centroid.x().trunc().into();
                 def.text_position.y = centroid.y().trunc().into();
                 self.caches[i].clear();
+                self.push_element_change(i, before, None);
                 self.layout_commited = false;
             }
             Message::ChangeNumberInput(input_type) => {
@@ -796,18 +1599,6 @@ impl NuhxBoard {
                     NumberInputType::Keycode(element, v) => {
                         self.number_input.keycode.insert(element, v);
                     }
-                    NumberInputType::RectanglePositionX(element, v) => {
-                        self.number_input.rectangle_position_x.insert(element, v);
-                    }
-                    NumberInputType::RectanglePositionY(element, v) => {
-                        self.number_input.rectangle_position_y.insert(element, v);
-                    }
-                    NumberInputType::RectangleSizeX(element, v) => {
-                        self.number_input.rectangle_size_x.insert(element, v);
-                    }
-                    NumberInputType::RectangleSizeY(element, v) => {
-                        self.number_input.rectangle_size_y.insert(element, v);
-                    }
                 }
             }
             Message::ChangeSelection(element, selection_type, selection) => {
@@ -823,6 +1614,7 @@ impl NuhxBoard {
             }
             Message::SwapBoundaries(element_i, left, right) => {
                 debug!(element_i, left, right, "Swapping boundaries");
+                let before = Box::new(self.layout.elements[element_i].clone());
                 let element = &mut self.layout.elements[element_i];
                 let Ok(def) = CommonDefinitionMut::try_from(element) else {
                     panic!("Cannot swap boundaries of mouse speed indicator");
@@ -830,56 +1622,33 @@ impl NuhxBoard {
                 def.boundaries.swap(left, right);
                 self.selections.boundary.insert(element_i, right);
                 self.caches[element_i].clear();
+                self.push_element_change(element_i, before, None);
                 self.layout_commited = false;
             }
-            Message::MakeRectangle(element_i) => {
-                debug!(element_i, "Making rectangle");
-                let element = &mut self.layout.elements[element_i];
+            Message::MakeRectangle {
+                index,
+                position,
+                size,
+            } => {
+                debug!(index, ?position, ?size, "Making rectangle");
+                let before = Box::new(self.layout.elements[index].clone());
+                let element = &mut self.layout.elements[index];
                 let Ok(def) = CommonDefinitionMut::try_from(element) else {
                     panic!("Cannot make rectangle of mouse speed indicator");
                 };
                 def.boundaries.clear();
-                let top_left = Coord {
-                    x: self
-                        .number_input
-                        .rectangle_position_x
-                        .get(&element_i)
-                        .copied()
-                        .unwrap_or_default(),
-                    y: self
-                        .number_input
-                        .rectangle_position_y
-                        .get(&element_i)
-                        .copied()
-                        .unwrap_or_default(),
-                };
-                let rect = Rect::new(
-                    top_left,
-                    top_left
-                        + Coord {
-                            x: self
-                                .number_input
-                                .rectangle_size_x
-                                .get(&element_i)
-                                .copied()
-                                .unwrap_or_default(),
-                            y: self
-                                .number_input
-                                .rectangle_size_y
-                                .get(&element_i)
-                                .copied()
-                                .unwrap_or_default(),
-                        },
-                );
+                let rect = Rect::new(position, position + size);
                 rect.exterior_coords_iter().for_each(|point| {
                     def.boundaries.push(point.into());
                 });
 
-                self.caches[element_i].clear();
+                self.caches[index].clear();
+                self.push_element_change(index, before, None);
+                self.layout_commited = false;
 
                 return self
                     .windows
-                    .close_all_of(Box::new(RectangleDialog { index: element_i }))
+                    .close_all_of(Box::new(RectangleDialog { index }))
                     .map(|_| Message::None);
             }
             Message::StartDetecting(element) => {
@@ -909,9 +1678,12 @@ impl NuhxBoard {
                         text_position: common.text_position,
                         key_codes: common.key_codes,
                         text: common.text,
+                        corner_radius: common.corner_radius,
+                        shape: common.shape,
                         shift_text: String::new(),
                         change_on_caps: false,
                     }));
+                self.push_element_added();
                 self.layout_commited = false;
             }
             Message::AddMouseKey => {
@@ -923,6 +1695,7 @@ impl NuhxBoard {
                     .extend(common.key_codes.iter().map(|c| (*c, cache.clone())));
 
                 self.layout.elements.push(BoardElement::MouseKey(common));
+                self.push_element_added();
                 self.layout_commited = false;
             }
             Message::AddMouseScroll => {
@@ -932,6 +1705,7 @@ impl NuhxBoard {
                 self.caches_by_scroll_button
                     .extend(common.key_codes.iter().map(|c| (*c, cache.clone())));
                 self.layout.elements.push(BoardElement::MouseScroll(common));
+                self.push_element_added();
                 self.layout_commited = false;
             }
             Message::AddMouseSpeedIndicator => {
@@ -946,12 +1720,13 @@ impl NuhxBoard {
                 self.layout
                     .elements
                     .push(BoardElement::MouseSpeedIndicator(def));
+                self.push_element_added();
                 self.layout_commited = false;
             }
             Message::RightClick(window) => {
                 debug!(%window, "Right click");
                 if window == self.main_window {
-                    self.right_click_pos = self.mouse_pos;
+                    self.right_click_pos = self.canvas_point(self.mouse_pos);
                 }
             }
             Message::MouseMoved {
@@ -963,40 +1738,21 @@ impl NuhxBoard {
                     self.mouse_pos = position;
                 }
             }
+            Message::WindowMoved {
+                position,
+                window_id,
+            } => {
+                trace!(?window_id, ?position, "Window moved");
+                self.windows.was_moved(window_id, position);
+            }
             Message::RemoveElement => {
-                debug!("Removing element");
-                let Some(i) = self.hovered_element.take() else {
-                    return Task::none();
-                };
-                let element = &self.layout.elements[i];
-                match element {
-                    BoardElement::KeyboardKey(def) => {
-                        for code in &def.key_codes {
-                            self.caches_by_keycode.remove(code);
-                        }
-                    }
-                    BoardElement::MouseKey(def) => {
-                        for code in &def.key_codes {
-                            self.caches_by_mouse_button.remove(code);
-                        }
-                    }
-                    BoardElement::MouseScroll(def) => {
-                        for code in &def.key_codes {
-                            self.caches_by_scroll_button.remove(code);
-                        }
-                    }
-                    BoardElement::MouseSpeedIndicator(def) => {
-                        self.mouse_speed_indicator_caches.remove(&def.id);
-                    }
-                }
-                self.caches_by_id.remove(&element.id());
-                self.caches.remove(i);
-                self.layout.elements.remove(i);
-                self.layout_commited = false;
+                debug!("Removing elements");
+                return self.remove_elements();
             }
             Message::Commit(action) => {
                 self.layout_commited = true;
                 self.style_commited = true;
+                recovery::clear();
                 match action {
                     Action::LoadKeyboard => {
                         return Task::batch([
@@ -1017,6 +1773,100 @@ impl NuhxBoard {
                     .close_all_of(Box::new(UnsavedChangesPopup(action)))
                     .map(|_| Message::None);
             }
+            Message::Autosave => {
+                if !self.recovery_pending {
+                    return Task::none();
+                }
+                debug!("Autosaving");
+                self.recovery_pending = false;
+                if let Err(e) = recovery::flush() {
+                    warn!(?e, "Failed to write recovery snapshot");
+                }
+            }
+            Message::RestoreRecovery => {
+                let close = self
+                    .windows
+                    .close_all_of(Box::new(RecoveryPopup))
+                    .map(|_| Message::None);
+                let Some(snapshot) = self.recovered.take() else {
+                    return close;
+                };
+                info!(
+                    category = snapshot.category,
+                    layout = ?snapshot.layout_name,
+                    "Restoring unsaved edits"
+                );
+                let mut tasks = vec![close];
+                // Saving writes the recovered edits over the keyboard and style they were made to,
+                // so they're only restored once both are found
+                let keyboard = match find_recovered_keyboard(&snapshot) {
+                    Ok(keyboard) => keyboard,
+                    Err(item) => {
+                        tasks.push(self.error(NuhxBoardError::RecoveryTarget(item)));
+                        return Task::batch(tasks);
+                    }
+                };
+                if !snapshot.category.is_empty() && snapshot.category != self.settings.category {
+                    tasks.push(
+                        self.update(Message::ChangeKeyboardCategory(snapshot.category.clone())),
+                    );
+                }
+                match keyboard {
+                    Some((layout, style)) => {
+                        tasks.push(self.update(Message::LoadLayout(layout)));
+                        if let Some(style) = style {
+                            tasks.push(self.update(Message::LoadStyle(style)));
+                        }
+                    }
+                    // The layout wasn't loaded from the keyboards directory, so it has to be saved
+                    // with "Save As"
+                    None => {
+                        self.layout_choice = None;
+                        self.settings.layout = None;
+                        self.style_choice = 0;
+                        self.settings.style = None;
+                        self.style_options.clear();
+                    }
+                }
+
+                self.layout = snapshot.layout;
+                self.style = snapshot.style;
+                self.edit_history = snapshot.edit_history;
+                self.history_depth = snapshot.history_depth;
+                self.layout_commited = false;
+                self.style_commited = false;
+                self.edit_mode = true;
+                self.rebuild_caches();
+                self.change_background_image(None);
+                self.sync_style_inputs();
+                self.clear_all_caches();
+                self.stage_recovery();
+                tasks.push(window::resize(
+                    self.main_window,
+                    iced::Size {
+                        width: self.layout.width,
+                        height: self.layout.height,
+                    },
+                ));
+                return Task::batch(tasks);
+            }
+            Message::DiscardRecovery => {
+                info!("Discarding recovered edits");
+                self.recovered = None;
+                recovery::clear();
+                return self
+                    .windows
+                    .close_all_of(Box::new(RecoveryPopup))
+                    .map(|_| Message::None);
+            }
+            Message::WindowUpdate(window, WindowMessage::Close) => {
+                info!(%window, "Closing window");
+                return iced::window::close(window);
+            }
+            Message::WindowUpdate(window, _) => {
+                debug!(%window, "Routing message to window");
+                return self.windows.update(window, message);
+            }
         }
         Task::none()
     }
@@ -1055,6 +1905,8 @@ impl NuhxBoard {
             ],
             text_position: self.right_click_pos.into(),
             key_codes: Vec::new(),
+            corner_radius: 0.0,
+            shape: KeyShape::Polygon,
         }
     }
 
@@ -1080,20 +1932,26 @@ impl NuhxBoard {
     pub fn subscription(&self) -> Subscription<Message> {
         Subscription::batch([
             subscription::from_recipe(RdevinSubscriber).map(Message::Listener),
-            iced::keyboard::on_key_press(|key, modifiers| {
-                if key == iced::keyboard::Key::Character(SmolStr::new("z"))
-                    && ((std::env::consts::OS == "macos" && modifiers.command())
-                        || modifiers.control())
-                {
-                    if modifiers.shift() {
-                        Some(Message::Redo)
-                    } else {
-                        Some(Message::Undo)
-                    }
-                } else {
-                    None
-                }
-            }),
+            // Every key press is learned while learning, so editor shortcuts are disabled
+            if self.learning.is_some() {
+                Subscription::none()
+            } else {
+                iced::event::listen_with(|event, status, window| match event {
+                    iced::Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                        key,
+                        modifiers,
+                        ..
+                    }) if status == iced::event::Status::Ignored => shortcut(key, modifiers)
+                        .map(|message| Message::Shortcut(window, Box::new(message))),
+                    _ => None,
+                })
+            },
+            if !self.layout_commited || !self.style_commited {
+                iced::time::every(Duration::from_secs(recovery::AUTOSAVE_INTERVAL))
+                    .map(|_| Message::Autosave)
+            } else {
+                Subscription::none()
+            },
             iced::window::close_events().map(Message::Closed),
             iced::window::close_requests().map(|_| Message::CloseRequested),
             iced::event::listen_with(|e, _, id| match e {
@@ -1106,16 +1964,255 @@ impl NuhxBoard {
                         window_id: id,
                     })
                 }
+                iced::Event::Window(iced::window::Event::Opened {
+                    position: Some(position),
+                    ..
+                })
+                | iced::Event::Window(iced::window::Event::Moved(position)) => {
+                    Some(Message::WindowMoved {
+                        position,
+                        window_id: id,
+                    })
+                }
                 _ => None,
             }),
         ])
     }
 
+    /// Windows that are saved on exit and reopened on startup, other than the main window, along
+    /// with the id of the element they're for if they refer to it by index. Those only used for
+    /// editing are only included in edit mode, and only for elements in the loaded layout.
+    fn restorable_windows(&self) -> Vec<RestorableWindow> {
+        let mut windows: Vec<RestorableWindow> = vec![(Box::new(SettingsWindow), None)];
+        if !self.edit_mode {
+            return windows;
+        }
+        windows.push((Box::new(KeyboardProperties), None));
+        windows.push((Box::new(KeyboardStyle), None));
+        for (index, element) in self.layout.elements.iter().enumerate() {
+            windows.push((Box::new(ElementProperties { index }), Some(element.id())));
+            windows.push((Box::new(ElementStyle { id: element.id() }), None));
+        }
+        windows
+    }
+
+    /// Returns the loaded keyboard category, layout and style.
+    fn selection(&self) -> Selection {
+        (
+            self.settings.category.clone(),
+            self.settings.layout.clone(),
+            self.settings.style.clone(),
+        )
+    }
+
+    /// Returns the ids and positions of all open windows that can be restored.
+    fn open_windows(&self) -> Vec<OpenWindow> {
+        let restorable = self.restorable_windows();
+        self.windows
+            .iter()
+            .filter_map(|(id, window)| {
+                let element = match restorable.iter().find(|(r, _)| r.id() == window.id()) {
+                    Some((_, element)) => *element,
+                    None if **window == Main => None,
+                    None => return None,
+                };
+                Some(OpenWindow {
+                    id: window.id(),
+                    position: self.windows.position(id).map(Into::into),
+                    element,
+                })
+            })
+            .collect()
+    }
+
+    /// Reopens the windows saved in [`Settings::open_windows`]. The main window is handled
+    /// separately, as it's opened before the layout is loaded.
+    fn restore_windows(&mut self) -> Task<Message> {
+        let restorable = self.restorable_windows();
+        let tasks: Vec<_> = self
+            .settings
+            .open_windows
+            .clone()
+            .into_iter()
+            .filter_map(|saved| {
+                let (window, _) = restorable.iter().find(|(r, element)| match saved.element {
+                    Some(id) => *element == Some(id),
+                    None => r.id() == saved.id,
+                })?;
+                let window = window.clone();
+                debug!(id = saved.id, "Restoring window");
+                let (_, task) = match saved.position {
+                    Some(position) => self.windows.open_at(window, position.into()),
+                    None => self.windows.open(window),
+                };
+                Some(task.map(|_| Message::None))
+            })
+            .collect();
+        Task::batch(tasks)
+    }
+
     fn error(&mut self, error: NuhxBoardError) -> iced::Task<Message> {
         let (_, command) = self.windows.open(Box::new(ErrorPopup { error }));
         command.map(|_| Message::None)
     }
 
+    /// Closes `dialog` and shows `result`'s error if there is one. Otherwise, rereads the keyboards
+    /// directory after `item` was renamed to `new`, or deleted if `new` is `None`.
+    fn finish_item_change(
+        &mut self,
+        result: std::io::Result<()>,
+        item: &KeyboardItem,
+        new: Option<&KeyboardItem>,
+        dialog: Box<dyn Window<Self, Theme, Message>>,
+    ) -> Task<Message> {
+        let close = self.windows.close_all_of(dialog).map(|_| Message::None);
+        if let Err(e) = result {
+            return Task::batch([
+                close,
+                self.error(NuhxBoardError::KeyboardFiles(Arc::new(e))),
+            ]);
+        }
+
+        let mut category = self.settings.category.clone();
+        let mut layout = self.layout_choice.map(|i| self.layout_options[i].clone());
+        let mut style = self.style_options.get(self.style_choice).cloned();
+        let new_name = new.map(|new| new.name().to_owned());
+        match item {
+            KeyboardItem::Category(name) if *name == category => match new_name {
+                Some(name) => category = name,
+                None => category.clear(),
+            },
+            KeyboardItem::Keyboard {
+                category: item_category,
+                name,
+            } if *item_category == category && layout.as_ref() == Some(name) => {
+                layout = new_name;
+            }
+            KeyboardItem::Style {
+                category: item_category,
+                keyboard,
+                name,
+                global,
+            } => {
+                let current = if *global {
+                    StyleChoice::Global(name.clone())
+                } else if *item_category == category && layout.as_ref() == Some(keyboard) {
+                    StyleChoice::Custom(name.clone())
+                } else {
+                    StyleChoice::Default
+                };
+                if current != StyleChoice::Default && style.as_ref() == Some(&current) {
+                    style = new_name.map(|name| match global {
+                        true => StyleChoice::Global(name),
+                        false => StyleChoice::Custom(name),
+                    });
+                }
+            }
+            _ => {}
+        }
+        Task::batch([
+            close,
+            self.refresh_keyboard_options(category, layout, style),
+        ])
+    }
+
+    /// Rereads the keyboards directory, selecting the given category, keyboard and style where
+    /// they exist. The loaded keyboard and style are kept, so that renaming them doesn't discard
+    /// changes.
+    fn refresh_keyboard_options(
+        &mut self,
+        category: String,
+        layout: Option<String>,
+        style: Option<StyleChoice>,
+    ) -> Task<Message> {
+        self.keyboard_category_options = read_categories();
+        self.layout_choice = None;
+        self.settings.layout = None;
+        self.style_choice = 0;
+        self.settings.style = None;
+        self.style_options.clear();
+        if !self.keyboard_category_options.contains(&category) {
+            // Keeps a deleted category from being loaded on startup
+            self.settings.category.clear();
+            self.layout_options.clear();
+            return Task::none();
+        }
+
+        self.settings.category = category.clone();
+        self.save_keyboard_as_category = category;
+        self.layout_options = read_layouts(&self.settings.category);
+        let Some(index) =
+            layout.and_then(|layout| self.layout_options.iter().position(|l| *l == layout))
+        else {
+            return Task::none();
+        };
+        self.layout_choice = Some(index);
+        self.settings.layout = Some(self.layout_options[index].clone());
+        self.save_layout_as_name = self.layout_options[index].clone();
+
+        self.style_options = read_styles(&self.settings.category, &self.layout_options[index]);
+        match style.and_then(|style| self.style_options.iter().position(|s| *s == style)) {
+            Some(style) => {
+                self.style_choice = style;
+                self.settings.style = self.style_options[style].setting();
+                Task::none()
+            }
+            // The loaded style was deleted
+            None => self.load_style(0),
+        }
+    }
+
+    /// Loads a category, and a layout and style within it by name, as saved in the settings or
+    /// given on the command line. Whatever doesn't exist is reported and left unloaded.
+    fn load_saved_keyboard(
+        &mut self,
+        category: String,
+        layout: Option<String>,
+        style: Option<String>,
+    ) -> Task<Message> {
+        if !KeyboardItem::Category(category.clone()).path().is_dir() {
+            self.settings.category.clear();
+            self.settings.layout = None;
+            self.settings.style = None;
+            return self.error(NuhxBoardError::ItemMissing(KeyboardItem::Category(
+                category,
+            )));
+        }
+        let category_task = self.update(Message::ChangeKeyboardCategory(category.clone()));
+        let Some(layout) = layout else {
+            return category_task;
+        };
+        let Some(index) = self.layout_options.iter().position(|l| *l == layout) else {
+            return Task::batch([
+                category_task,
+                self.error(NuhxBoardError::ItemMissing(KeyboardItem::Keyboard {
+                    category,
+                    name: layout,
+                })),
+            ]);
+        };
+        let layout_task = self.load_layout(index);
+
+        let choice = StyleChoice::from_setting(style.as_deref());
+        let style_task = match self.style_options.iter().position(|s| *s == choice) {
+            Some(style) => self.load_style(style),
+            // Falls back to the default style
+            None => {
+                let global = choice.is_global();
+                Task::batch([
+                    self.load_style(0),
+                    self.error(NuhxBoardError::ItemMissing(KeyboardItem::Style {
+                        category,
+                        keyboard: layout,
+                        name: choice.name(),
+                        global,
+                    })),
+                ])
+            }
+        };
+        Task::batch([category_task, layout_task, style_task])
+    }
+
     fn load_layout(&mut self, index: usize) -> Task<Message> {
         if index >= self.layout_options.len() {
             return self.error(NuhxBoardError::LayoutOpen(Arc::new(std::io::Error::new(
@@ -1125,7 +2222,10 @@ impl NuhxBoard {
         }
 
         self.edit_mode = false;
-        self.settings.layout_index = index;
+        self.learning = None;
+        self.zoom = 1.0;
+        self.pan = iced::Vector::ZERO;
+        self.settings.layout = Some(self.layout_options[index].clone());
 
         self.layout_choice = Some(index);
         self.style = Style::default();
@@ -1151,86 +2251,13 @@ impl NuhxBoard {
             }
         };
 
-        self.caches.clear();
-        self.caches_by_keycode.clear();
-        self.caches_by_mouse_button.clear();
-        self.caches_by_scroll_button.clear();
-        self.mouse_speed_indicator_caches.clear();
-        for e in &self.layout.elements {
-            let cache = ElementCache::new_rc();
-            self.caches.push(cache.clone());
-            self.caches_by_id.insert(e.id(), cache.clone());
+        self.edit_history.clear();
+        self.history_depth = 0;
+        self.last_edit = None;
+        self.selected_elements.clear();
+        self.rebuild_caches();
 
-            match e {
-                BoardElement::KeyboardKey(def) => {
-                    self.caches_by_keycode
-                        .extend(def.key_codes.iter().map(|c| (*c, cache.clone())));
-                }
-                BoardElement::MouseKey(def) => {
-                    self.caches_by_mouse_button
-                        .extend(def.key_codes.iter().map(|c| (*c, cache.clone())));
-                }
-                BoardElement::MouseScroll(def) => {
-                    self.caches_by_scroll_button
-                        .extend(def.key_codes.iter().map(|c| (*c, cache.clone())));
-                }
-                BoardElement::MouseSpeedIndicator(def) => {
-                    self.mouse_speed_indicator_caches.insert(def.id, cache);
-                }
-            }
-        }
-
-        self.style_options = vec![StyleChoice::Default];
-        self.style_options.append(
-            &mut fs::read_dir(
-                KEYBOARDS_PATH
-                    .join(&self.settings.category)
-                    .join(&self.layout_options[index]),
-            )
-            .unwrap()
-            .filter_map(|r| {
-                let entry = r.unwrap();
-                if entry.file_type().unwrap().is_file()
-                    && entry.path().extension() == Some(std::ffi::OsStr::new("style"))
-                {
-                    Some(StyleChoice::Custom(
-                        entry
-                            .path()
-                            .file_stem()
-                            .unwrap()
-                            .to_str()
-                            .unwrap()
-                            .to_owned(),
-                    ))
-                } else {
-                    None
-                }
-            })
-            .collect(),
-        );
-        self.style_options.append(
-            &mut fs::read_dir(KEYBOARDS_PATH.join("global"))
-                .unwrap()
-                .filter_map(|r| {
-                    let entry = r.unwrap();
-                    if entry.file_type().unwrap().is_file()
-                        && entry.path().extension() == Some(std::ffi::OsStr::new("style"))
-                    {
-                        Some(StyleChoice::Custom(
-                            entry
-                                .path()
-                                .file_stem()
-                                .unwrap()
-                                .to_str()
-                                .unwrap()
-                                .to_owned(),
-                        ))
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
-        );
+        self.style_options = read_styles(&self.settings.category, &self.layout_options[index]);
         self.style_choice = 0;
 
         window::resize(
@@ -1261,13 +2288,13 @@ impl NuhxBoard {
                         self.layout.height as u32,
                         image::imageops::FilterType::Nearest,
                     )
-                    .save(KEYBOARDS_PATH.parent().unwrap().join("background.png"))
+                    .save(background_path())
                     .unwrap();
             } else {
-                let _ = fs::remove_file(KEYBOARDS_PATH.parent().unwrap().join("background.png"));
+                let _ = fs::remove_file(background_path());
             }
         } else {
-            let _ = fs::remove_file(KEYBOARDS_PATH.parent().unwrap().join("background.png"));
+            let _ = fs::remove_file(background_path());
         }
     }
 
@@ -1279,7 +2306,7 @@ impl NuhxBoard {
             ))));
         }
 
-        self.settings.style = style;
+        self.settings.style = self.style_options[style].setting();
 
         self.style_choice = style;
 
@@ -1317,7 +2344,15 @@ impl NuhxBoard {
         self.change_background_image(None);
 
         self.save_style_as_name = self.style_options[style].name();
+        self.drop_style_changes();
+
+        self.sync_style_inputs();
+        self.clear_all_caches();
+        Task::none()
+    }
 
+    /// Updates the style text inputs that mirror the current style.
+    fn sync_style_inputs(&mut self) {
         self.text_input.keyboard_background_image = self
             .style
             .background_image_file_name
@@ -1337,9 +2372,6 @@ impl NuhxBoard {
             .background_image_file_name
             .clone()
             .unwrap_or_default();
-
-        self.clear_all_caches();
-        Task::none()
     }
 
     #[instrument(level = "trace", skip_all, fields(event = ?event.event_type))]
@@ -1358,10 +2390,14 @@ impl NuhxBoard {
                 let Some(keycode) = win_keycode_from_key(key) else {
                     return self.error(NuhxBoardError::UnknownKey(key));
                 };
-                self.pressed_keys.insert(keycode, Instant::now());
+                let repeat = self.pressed_keys.insert(keycode, Instant::now()).is_some();
                 if let Some(cache) = self.caches_by_keycode.get(&keycode) {
                     cache.clear();
                 }
+                // Held keys repeat, but should only be learned once
+                if !repeat && self.learning.is_some() {
+                    self.learn_key(keycode, event.unicode.and_then(|unicode| unicode.name));
+                }
                 if !self.detecting.is_empty() {
                     captured_key = Some(keycode);
                 }
@@ -1750,6 +2786,38 @@ impl NuhxBoard {
         self.style_commited = false;
     }
 
+    fn rebuild_caches(&mut self) {
+        self.caches.clear();
+        self.caches_by_id.clear();
+        self.caches_by_keycode.clear();
+        self.caches_by_mouse_button.clear();
+        self.caches_by_scroll_button.clear();
+        self.mouse_speed_indicator_caches.clear();
+        for e in &self.layout.elements {
+            let cache = ElementCache::new_rc();
+            self.caches.push(cache.clone());
+            self.caches_by_id.insert(e.id(), cache.clone());
+
+            match e {
+                BoardElement::KeyboardKey(def) => {
+                    self.caches_by_keycode
+                        .extend(def.key_codes.iter().map(|c| (*c, cache.clone())));
+                }
+                BoardElement::MouseKey(def) => {
+                    self.caches_by_mouse_button
+                        .extend(def.key_codes.iter().map(|c| (*c, cache.clone())));
+                }
+                BoardElement::MouseScroll(def) => {
+                    self.caches_by_scroll_button
+                        .extend(def.key_codes.iter().map(|c| (*c, cache.clone())));
+                }
+                BoardElement::MouseSpeedIndicator(def) => {
+                    self.mouse_speed_indicator_caches.insert(def.id, cache);
+                }
+            }
+        }
+    }
+
     fn clear_all_caches(&self) {
         for c in &self.caches {
             c.clear();
@@ -1762,13 +2830,586 @@ impl NuhxBoard {
         }
     }
 
-    fn apply_change(&mut self, change: Change, undo: bool) {
+    fn update_selection(&mut self, selection: BTreeSet<usize>) {
+        for index in self.selected_elements.symmetric_difference(&selection) {
+            self.caches[*index].clear();
+        }
+        self.selected_elements = selection;
+    }
+
+    /// Converts a point in the main window to layout coordinates, undoing the edit canvas' zoom
+    /// and pan.
+    pub fn canvas_point(&self, point: iced::Point) -> iced::Point {
+        iced::Point {
+            x: (point.x - self.pan.x) / self.zoom,
+            y: (point.y - self.pan.y) / self.zoom,
+        }
+    }
+
+    /// The elements that editing commands act on: the selected elements, or the hovered element
+    /// if nothing is selected.
+    pub fn target_elements(&self) -> Vec<usize> {
+        if self.selected_elements.is_empty() {
+            self.hovered_element.into_iter().collect()
+        } else {
+            self.selected_elements.iter().copied().collect()
+        }
+    }
+
+    fn clipboard_elements(&self) -> ClipboardElements {
+        let elements: Vec<_> = self
+            .target_elements()
+            .into_iter()
+            .map(|index| self.layout.elements[index].clone())
+            .collect();
+        let element_styles = elements
+            .iter()
+            .filter_map(|element| {
+                self.style
+                    .element_styles
+                    .get(&element.id())
+                    .map(|style| (element.id(), style.clone()))
+            })
+            .collect();
+        ClipboardElements {
+            elements,
+            element_styles,
+        }
+    }
+
+    /// Removes the target elements as a single change.
+    fn remove_elements(&mut self) -> Task<Message> {
+        if !self.edit_mode {
+            return Task::none();
+        }
+        // Removed from the back, so that the remaining indices stay valid
+        let changes: Vec<_> = self
+            .target_elements()
+            .into_iter()
+            .rev()
+            .map(|index| Change::RemoveElement {
+                index,
+                element: Box::new(self.layout.elements[index].clone()),
+            })
+            .collect();
+        if changes.is_empty() {
+            return Task::none();
+        }
+        self.apply_new_change(Change::Group(changes))
+    }
+
+    /// Serializes the target elements for the clipboard.
+    fn copy_elements(&self) -> Option<String> {
+        if !self.edit_mode {
+            return None;
+        }
+        let clipboard = self.clipboard_elements();
+        if clipboard.elements.is_empty() {
+            return None;
+        }
+        serde_json::to_string_pretty(&clipboard).ok()
+    }
+
+    /// Adds copies of the given elements, moved by `delta`, as a single change. The copies are
+    /// given new ids, and then selected.
+    fn add_elements(&mut self, clipboard: ClipboardElements, delta: Coord<f32>) -> Task<Message> {
+        let start = self.layout.elements.len();
+        let mut id = self.new_id();
+        let mut style = self.style.clone();
+        let mut changes = Vec::with_capacity(clipboard.elements.len() + 1);
+        for (offset, mut element) in clipboard.elements.into_iter().enumerate() {
+            if let Some(element_style) = clipboard.element_styles.get(&element.id()) {
+                style.element_styles.insert(id, element_style.clone());
+            }
+            *element.id_mut() = id;
+            id += 1;
+            element.translate(delta, true);
+            changes.push(Change::AddElement {
+                index: start + offset,
+                element: Box::new(element),
+            });
+        }
+        if changes.is_empty() {
+            return Task::none();
+        }
+        if !clipboard.element_styles.is_empty() {
+            changes.push(Change::EditStyle {
+                before: Box::new(self.style.clone()),
+                after: Box::new(style),
+            });
+        }
+
+        let task = self.apply_new_change(Change::Group(changes));
+        self.update_selection((start..self.layout.elements.len()).collect());
+        task
+    }
+
+    /// Sorts elements into rows from top to bottom, and each row from left to right.
+    fn reading_order(&self, mut indices: Vec<usize>) -> Vec<usize> {
+        let center = |index: &usize| self.layout.elements[*index].bounding_rect().center();
+        indices.sort_by(|a, b| center(a).y.total_cmp(&center(b).y));
+        let mut rows: Vec<Vec<usize>> = Vec::new();
+        for index in indices {
+            match rows.last_mut() {
+                Some(row)
+                    if (center(&index).y - center(&row[0]).y).abs() < DEFAULT_KEY_SIZE / 2.0 =>
+                {
+                    row.push(index);
+                }
+                _ => rows.push(vec![index]),
+            }
+        }
+        rows.into_iter()
+            .flat_map(|mut row| {
+                row.sort_by(|a, b| center(a).x.total_cmp(&center(b).x));
+                row
+            })
+            .collect()
+    }
+
+    /// Replaces the learning state, redrawing the old and new current elements.
+    fn set_learning(&mut self, learning: Option<Learning>) {
+        let previous = self.learning.as_ref().and_then(Learning::current);
+        self.learning = learning;
+        let current = self.learning.as_ref().and_then(Learning::current);
+        for index in previous.into_iter().chain(current) {
+            self.caches[index].clear();
+        }
+    }
+
+    /// Moves on to the next element to learn, and stops once there are none left.
+    fn advance_learning(&mut self) {
+        let Some(mut learning) = self.learning.clone() else {
+            return;
+        };
+        learning.position += 1;
+        if learning.current().is_some() {
+            self.set_learning(Some(learning));
+        } else {
+            info!("Finished learning keycodes");
+            self.set_learning(None);
+        }
+    }
+
+    /// Assigns a key press to the element currently being learned. Its legends are taken from the
+    /// first keycode table that agrees with what the key types under the system keymap, if it
+    /// types anything printable.
+    fn learn_key(&mut self, keycode: u32, typed: Option<String>) {
+        let Some(index) = self.learning.as_ref().and_then(Learning::current) else {
+            return;
+        };
+        debug!(index, keycode, ?typed, "Learning key");
+        let before = Box::new(self.layout.elements[index].clone());
+        if let BoardElement::KeyboardKey(def) = &mut self.layout.elements[index] {
+            def.key_codes = vec![keycode];
+            if let Some(typed) = typed.filter(|typed| {
+                !typed.is_empty() && typed.chars().all(|c| !c.is_control() && !c.is_whitespace())
+            }) {
+                // The system keymap only tells what the key typed, with or without shift
+                let legends = Locale::ALL.into_iter().find_map(|locale| {
+                    labels::label(keycode, locale)
+                        .filter(|(text, shift_text)| *text == typed || *shift_text == typed)
+                });
+                (def.text, def.shift_text) = legends.unwrap_or_else(|| (typed.clone(), typed));
+            }
+        }
+        self.push_element_change(index, before, None);
+        self.rebuild_caches();
+        self.layout_commited = false;
+        self.advance_learning();
+    }
+
+    /// Sets legends from the keycode table for `locale`, as a single change. If `from` is given,
+    /// keys with legends other than those for `from` are assumed to be customized, and are left
+    /// alone.
+    fn label_keys(&mut self, from: Option<Locale>, locale: Locale) -> Task<Message> {
+        if !self.edit_mode {
+            return Task::none();
+        }
+        let indices: Vec<usize> = if self.selected_elements.is_empty() {
+            (0..self.layout.elements.len()).collect()
+        } else {
+            self.selected_elements.iter().copied().collect()
+        };
+        let changes: Vec<_> = indices
+            .into_iter()
+            .filter_map(|index| {
+                let BoardElement::KeyboardKey(def) = &self.layout.elements[index] else {
+                    return None;
+                };
+                let keycode = *def.key_codes.first()?;
+                // Layouts differ in whether letters are labelled in upper or lower case, which is
+                // kept for letters. Other legends, such as "Enter", are taken as they are.
+                let mut uppercase = None;
+                if let Some(from) = from {
+                    let (text, shift_text) = labels::label(keycode, from)?;
+                    if def.text.to_lowercase() != text.to_lowercase()
+                        || def.shift_text.to_lowercase() != shift_text.to_lowercase()
+                    {
+                        return None;
+                    }
+                    if is_letter(&text) && def.text != text {
+                        uppercase = Some(def.text.chars().all(char::is_uppercase));
+                    }
+                }
+                let (mut text, shift_text) = labels::label(keycode, locale)?;
+                match uppercase {
+                    Some(true) if is_letter(&text) => text = text.to_uppercase(),
+                    Some(false) if is_letter(&text) => text = text.to_lowercase(),
+                    _ => {}
+                }
+                if def.text == text && def.shift_text == shift_text {
+                    return None;
+                }
+
+                let before = self.layout.elements[index].clone();
+                let mut after = before.clone();
+                if let BoardElement::KeyboardKey(def) = &mut after {
+                    def.text = text;
+                    def.shift_text = shift_text;
+                }
+                Some(Change::EditElement {
+                    index,
+                    before: Box::new(before),
+                    after: Box::new(after),
+                })
+            })
+            .collect();
+        debug!(keys = changes.len(), "Labelled keys");
+        if changes.is_empty() {
+            return Task::none();
+        }
+        self.apply_new_change(Change::Group(changes))
+    }
+
+    /// Combines the shapes of the selected elements into the one the context menu was opened on,
+    /// as a single change. When subtracting, that's the shape the others are cut from.
+    fn combine_shapes(&mut self, operation: ShapeOperation) -> Task<Message> {
+        let mut indices = self.target_elements();
+        if !self.edit_mode || indices.len() < 2 {
+            return Task::none();
+        }
+        match self
+            .hovered_element
+            .filter(|hovered| indices.contains(hovered))
+        {
+            Some(base) => {
+                indices.retain(|index| *index != base);
+                indices.insert(0, base);
+            }
+            None if operation == ShapeOperation::Subtract => {
+                return self.error(NuhxBoardError::ShapeOperation(
+                    "right-click the selected shape to cut the others from",
+                ));
+            }
+            None => {}
+        }
+
+        let mut polygons = Vec::with_capacity(indices.len());
+        let mut key_codes = Vec::new();
+        let kind = std::mem::discriminant(&self.layout.elements[indices[0]]);
+        for index in &indices {
+            let element = &self.layout.elements[*index];
+            let Ok(def) = CommonDefinitionRef::try_from(element) else {
+                return self.error(NuhxBoardError::ShapeOperation(
+                    "mouse speed indicators have no polygon",
+                ));
+            };
+            // Keyboard keys, mouse buttons and scroll directions each number their keycodes
+            // separately
+            if std::mem::discriminant(element) != kind {
+                return self.error(NuhxBoardError::ShapeOperation(
+                    "the elements are of different types",
+                ));
+            }
+            // Rounded corners and ellipses are combined as drawn
+            polygons.push(def.outline());
+            // Subtracted elements only cut the shape
+            if operation != ShapeOperation::Subtract || *index == indices[0] {
+                for code in def.key_codes {
+                    if !key_codes.contains(code) {
+                        key_codes.push(*code);
+                    }
+                }
+            }
+        }
+
+        let mut polygons = polygons.into_iter();
+        let first = MultiPolygon::new(vec![polygons.next().unwrap()]);
+        let result = polygons.fold(first, |result, polygon| match operation {
+            ShapeOperation::Union => result.union(&polygon),
+            ShapeOperation::Subtract => result.difference(&polygon),
+            ShapeOperation::Intersect => result.intersection(&polygon),
+        });
+        let [polygon] = result.0.as_slice() else {
+            return self.error(NuhxBoardError::ShapeOperation(
+                "the result isn't a single shape",
+            ));
+        };
+        if !polygon.interiors().is_empty() {
+            return self.error(NuhxBoardError::ShapeOperation("the result has holes"));
+        }
+
+        let base = indices[0];
+        let before = self.layout.elements[base].clone();
+        let mut after = before.clone();
+        let def = CommonDefinitionMut::try_from(&mut after).unwrap();
+        *def.boundaries = polygon.exterior().coords().map(|c| (*c).into()).collect();
+        // geo closes rings by repeating the first point
+        def.boundaries.pop();
+        *def.corner_radius = 0.0;
+        *def.shape = KeyShape::Polygon;
+        *def.key_codes = key_codes;
+        if let Some(centroid) = polygon.centroid() {
+            *def.text_position = centroid.0.into();
+        }
+
+        let mut changes = vec![Change::EditElement {
+            index: base,
+            before: Box::new(before),
+            after: Box::new(after),
+        }];
+        // Removed from the back, so that the remaining indices stay valid
+        let mut removed = indices[1..].to_vec();
+        removed.sort_unstable();
+        let selected = base - removed.iter().filter(|index| **index < base).count();
+        let mut style = self.style.clone();
+        for index in removed.into_iter().rev() {
+            let element = self.layout.elements[index].clone();
+            style.element_styles.remove(&element.id());
+            changes.push(Change::RemoveElement {
+                index,
+                element: Box::new(element),
+            });
+        }
+        if style.element_styles.len() != self.style.element_styles.len() {
+            changes.push(Change::EditStyle {
+                before: Box::new(self.style.clone()),
+                after: Box::new(style),
+            });
+        }
+        let task = self.apply_new_change(Change::Group(changes));
+        self.update_selection(BTreeSet::from([selected]));
+        task
+    }
+
+    /// Moves the selected elements according to `align` as a single change.
+    fn align(&mut self, align: Align) -> Task<Message> {
+        let mut bounds: Vec<(usize, Rect<f32>)> = self
+            .selected_elements
+            .iter()
+            .map(|index| (*index, self.layout.elements[*index].bounding_rect()))
+            .collect();
+        let Some(group) = group_bounding_rect(
+            self.selected_elements
+                .iter()
+                .map(|index| &self.layout.elements[*index]),
+        ) else {
+            return Task::none();
+        };
+
+        let deltas: Vec<(usize, Coord<f32>)> = match align {
+            Align::Left => bounds
+                .iter()
+                .map(|(i, rect)| (*i, Coord::from((group.min().x - rect.min().x, 0.0))))
+                .collect(),
+            Align::Right => bounds
+                .iter()
+                .map(|(i, rect)| (*i, Coord::from((group.max().x - rect.max().x, 0.0))))
+                .collect(),
+            Align::Top => bounds
+                .iter()
+                .map(|(i, rect)| (*i, Coord::from((0.0, group.min().y - rect.min().y))))
+                .collect(),
+            Align::Bottom => bounds
+                .iter()
+                .map(|(i, rect)| (*i, Coord::from((0.0, group.max().y - rect.max().y))))
+                .collect(),
+            Align::CenterHorizontally => bounds
+                .iter()
+                .map(|(i, rect)| (*i, Coord::from((group.center().x - rect.center().x, 0.0))))
+                .collect(),
+            Align::CenterVertically => bounds
+                .iter()
+                .map(|(i, rect)| (*i, Coord::from((0.0, group.center().y - rect.center().y))))
+                .collect(),
+            Align::DistributeHorizontally => {
+                bounds.sort_by(|(_, a), (_, b)| a.min().x.total_cmp(&b.min().x));
+                let widths: f32 = bounds.iter().map(|(_, rect)| rect.width()).sum();
+                let gap = (group.width() - widths) / (bounds.len() - 1).max(1) as f32;
+                let mut x = group.min().x;
+                bounds
+                    .iter()
+                    .map(|(i, rect)| {
+                        let delta = Coord::from((x - rect.min().x, 0.0));
+                        x += rect.width() + gap;
+                        (*i, delta)
+                    })
+                    .collect()
+            }
+            Align::DistributeVertically => {
+                bounds.sort_by(|(_, a), (_, b)| a.min().y.total_cmp(&b.min().y));
+                let heights: f32 = bounds.iter().map(|(_, rect)| rect.height()).sum();
+                let gap = (group.height() - heights) / (bounds.len() - 1).max(1) as f32;
+                let mut y = group.min().y;
+                bounds
+                    .iter()
+                    .map(|(i, rect)| {
+                        let delta = Coord::from((0.0, y - rect.min().y));
+                        y += rect.height() + gap;
+                        (*i, delta)
+                    })
+                    .collect()
+            }
+        };
+
+        let changes: Vec<Change> = deltas
+            .into_iter()
+            .filter(|(_, delta)| *delta != Coord::zero())
+            .map(|(index, delta)| Change::MoveElements {
+                indices: vec![index],
+                delta,
+            })
+            .collect();
+        if changes.is_empty() {
+            return Task::none();
+        }
+        self.apply_new_change(Change::Group(changes))
+    }
+
+    /// Adds a change to the edit history, discarding any changes that were undone.
+    fn push_change(&mut self, change: Change) {
+        self.push_edit(change, None);
+    }
+
+    /// Adds a change made to `target` to the edit history. If the last change was made to the same
+    /// target, the two are merged, so that they're undone together.
+    fn push_edit(&mut self, change: Change, target: Option<EditTarget>) {
+        if self.history_depth > 0 {
+            self.edit_history
+                .truncate(self.edit_history.len() - self.history_depth);
+            self.history_depth = 0;
+            self.last_edit = None;
+        }
+        let coalesce = target.is_some() && target == self.last_edit;
+        match (self.edit_history.last_mut(), change) {
+            (
+                Some(Change::EditElement { index, after, .. }),
+                Change::EditElement {
+                    index: new_index,
+                    after: new_after,
+                    ..
+                },
+            ) if coalesce && *index == new_index => *after = new_after,
+            (
+                Some(Change::EditStyle { after, .. }),
+                Change::EditStyle {
+                    after: new_after, ..
+                },
+            ) if coalesce => *after = new_after,
+            (_, change) => self.edit_history.push(change),
+        }
+        self.last_edit = target;
+        self.stage_recovery();
+    }
+
+    /// Applies a change that hasn't been made yet and adds it to the edit history.
+    fn apply_new_change(&mut self, change: Change) -> Task<Message> {
+        // The change is applied first so that the snapshot staged for recovery includes it
+        let task = self.apply_change(change.clone(), false);
+        self.push_change(change);
+        task
+    }
+
+    /// Stages the current edits to be written for crash recovery.
+    fn stage_recovery(&mut self) {
+        self.recovery_pending = true;
+        recovery::stage(Snapshot {
+            category: self.settings.category.clone(),
+            layout_name: self.layout_choice.map(|i| self.layout_options[i].clone()),
+            style_choice: self.style_options.get(self.style_choice).cloned(),
+            layout: self.layout.clone(),
+            style: self.style.clone(),
+            edit_history: self.edit_history.clone(),
+            history_depth: self.history_depth,
+        });
+    }
+
+    /// Removes edits to the style from the edit history, keeping those to the layout.
+    fn drop_style_changes(&mut self) {
+        fn without_style(change: Change) -> Option<Change> {
+            match change {
+                Change::EditStyle { .. } => None,
+                Change::Group(changes) => {
+                    let changes: Vec<_> = changes.into_iter().filter_map(without_style).collect();
+                    (!changes.is_empty()).then_some(Change::Group(changes))
+                }
+                change => Some(change),
+            }
+        }
+
+        let undone: Vec<_> = self
+            .edit_history
+            .split_off(self.edit_history.len() - self.history_depth)
+            .into_iter()
+            .filter_map(without_style)
+            .collect();
+        self.history_depth = undone.len();
+        self.edit_history = std::mem::take(&mut self.edit_history)
+            .into_iter()
+            .filter_map(without_style)
+            .chain(undone)
+            .collect();
+        self.last_edit = None;
+    }
+
+    /// Records an edit to the element at `index`, given its state from before the edit, and what it
+    /// was made to if further edits to the same property should be merged into it.
+    fn push_element_change(
+        &mut self,
+        index: usize,
+        before: Box<BoardElement>,
+        target: Option<EditTarget>,
+    ) {
+        let after = Box::new(self.layout.elements[index].clone());
+        self.push_edit(
+            Change::EditElement {
+                index,
+                before,
+                after,
+            },
+            target,
+        );
+    }
+
+    /// Records the addition of the last element in the layout.
+    fn push_element_added(&mut self) {
+        let index = self.layout.elements.len() - 1;
+        let element = Box::new(self.layout.elements[index].clone());
+        self.push_change(Change::AddElement { index, element });
+    }
+
+    /// Records an edit to the style, given its state from before the edit, and what it was made to if
+    /// further edits to the same setting should be merged into it.
+    fn push_style_change(&mut self, before: Box<Style>, target: Option<EditTarget>) {
+        let after = Box::new(self.style.clone());
+        self.push_edit(Change::EditStyle { before, after }, target);
+    }
+
+    fn apply_change(&mut self, change: Change, undo: bool) -> Task<Message> {
         let signum = if undo { -1.0 } else { 1.0 };
-        let clear_index = match change {
-            Change::MoveElement { index, delta } => {
-                self.layout.elements[index]
-                    .translate(delta * signum, self.settings.update_text_position);
-                index
+        // Adding an element or vertex is undone by removing it, and vice versa
+        let inserting = matches!(
+            change,
+            Change::RemoveElement { .. } | Change::RemoveVertex { .. }
+        ) == undo;
+        match change {
+            Change::MoveElements { indices, delta } => {
+                for index in indices {
+                    self.layout.elements[index]
+                        .translate(delta * signum, self.settings.update_text_position);
+                    self.caches[index].clear();
+                }
             }
             Change::MoveFace { index, face, delta } => {
                 match CommonDefinitionMut::try_from(&mut self.layout.elements[index]) {
@@ -1780,7 +3421,6 @@ impl NuhxBoard {
                     }
                 }
                 self.caches[index].clear();
-                index
             }
             Change::MoveVertex {
                 index,
@@ -1790,13 +3430,194 @@ impl NuhxBoard {
                 let def = CommonDefinitionMut::try_from(&mut self.layout.elements[index]).unwrap();
                 def.boundaries[vertex] += delta * signum;
                 self.caches[index].clear();
-                index
             }
-        };
-        self.caches[clear_index].clear();
+            Change::InsertVertex {
+                index,
+                vertex,
+                position,
+            }
+            | Change::RemoveVertex {
+                index,
+                vertex,
+                position,
+            } if inserting => {
+                let def = CommonDefinitionMut::try_from(&mut self.layout.elements[index]).unwrap();
+                def.boundaries.insert(vertex, position);
+                self.caches[index].clear();
+            }
+            Change::InsertVertex { index, vertex, .. }
+            | Change::RemoveVertex { index, vertex, .. } => {
+                let def = CommonDefinitionMut::try_from(&mut self.layout.elements[index]).unwrap();
+                def.boundaries.remove(vertex);
+                self.caches[index].clear();
+            }
+            Change::EditElement {
+                index,
+                before,
+                after,
+            } => {
+                self.layout.elements[index] = *if undo { before } else { after };
+                // The element's keycodes may have changed
+                self.rebuild_caches();
+            }
+            Change::AddElement { index, element } | Change::RemoveElement { index, element }
+                if inserting =>
+            {
+                self.layout.elements.insert(index, *element);
+                self.selected_elements.clear();
+                // Indices have shifted
+                self.learning = None;
+                self.rebuild_caches();
+            }
+            Change::AddElement { index, .. } | Change::RemoveElement { index, .. } => {
+                self.layout.elements.remove(index);
+                self.selected_elements.clear();
+                self.learning = None;
+                self.rebuild_caches();
+                self.hovered_element = None;
+                self.layout_commited = false;
+                // The last index no longer refers to an element
+                return self
+                    .windows
+                    .close_all_of(Box::new(ElementProperties {
+                        index: self.layout.elements.len(),
+                    }))
+                    .map(|_| Message::None);
+            }
+            Change::Resize { before, after } => {
+                let size = if undo { before } else { after };
+                self.layout.width = size.width;
+                self.layout.height = size.height;
+                self.layout_commited = false;
+                return window::resize(self.main_window, size);
+            }
+            Change::EditStyle { before, after } => {
+                self.style = *if undo { before } else { after };
+                self.change_background_image(None);
+                self.sync_style_inputs();
+                self.clear_all_caches();
+                self.style_commited = false;
+                return Task::none();
+            }
+            Change::Group(mut changes) => {
+                if undo {
+                    changes.reverse();
+                }
+                let tasks: Vec<_> = changes
+                    .into_iter()
+                    .map(|change| self.apply_change(change, undo))
+                    .collect();
+                return Task::batch(tasks);
+            }
+        }
+        self.layout_commited = false;
+        Task::none()
     }
 }
 
 fn immediate_task(message: Message) -> Task<Message> {
     Task::perform(std::future::ready(message), |m| m)
 }
+
+/// Returns whether a legend is a single letter, whose case can be changed to match other legends.
+fn is_letter(legend: &str) -> bool {
+    let mut chars = legend.chars();
+    chars.next().is_some_and(char::is_alphabetic) && chars.next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an empty directory in a temporary directory of its own.
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nuhxboard-{test}-{}", std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn write_file_rotates_backups() {
+        let dir = temp_dir("write-file");
+        let path = dir.join("keyboard.json");
+        for contents in ["1", "2", "3", "4"] {
+            write_file(&path, contents.as_bytes(), 2).unwrap();
+        }
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("keyboard.json"), "4");
+        assert_eq!(read("keyboard.json.1.bak"), "3");
+        assert_eq!(read("keyboard.json.2.bak"), "2");
+        assert!(!dir.join("keyboard.json.3.bak").exists());
+        assert!(!dir.join(".keyboard.json.tmp").exists());
+    }
+
+    #[test]
+    fn write_file_without_backups() {
+        let dir = temp_dir("write-file-no-backups");
+        let path = dir.join("Dark.style");
+        write_file(&path, b"old", 0).unwrap();
+        write_file(&path, b"new", 0).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let error = write_file(&dir.join(".."), b"", 0).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn migrate_indices_to_names() {
+        // The keyboards directory is global, so point the config directory at a temporary one
+        let config = CONFIG_DIR.get_or_init(|| temp_dir("migrate-indices"));
+        assert!(config.starts_with(std::env::temp_dir()));
+        let category = KEYBOARDS_PATH.join("Category");
+        fs::create_dir_all(category.join("images")).unwrap();
+        fs::create_dir_all(category.join("A")).unwrap();
+        fs::create_dir_all(category.join("B")).unwrap();
+        fs::write(category.join("B/Dark.style"), "{}").unwrap();
+        fs::create_dir_all(KEYBOARDS_PATH.join("global")).unwrap();
+        fs::write(KEYBOARDS_PATH.join("global/Plain.style"), "{}").unwrap();
+
+        // Settings as saved by older versions
+        let settings = |keyboard: usize, style: usize| -> Settings {
+            serde_json::from_value(serde_json::json!({
+                "capitalization": "Follow",
+                "follow_for_caps_sensitive": false,
+                "follow_for_caps_insensitive": false,
+                "category": "Category",
+                "keyboard": keyboard,
+                "style": style,
+                "mouse_from_center": false,
+                "mouse_sensitivity": 50.0,
+                "min_press_time": 0,
+                "scroll_hold_time": 100,
+                "window_title": "NuhxBoard",
+                "display_choice": { "id": 0, "primary": true },
+                "update_text_position": true,
+            }))
+            .unwrap()
+        };
+
+        let mut migrated = settings(1, 1);
+        migrate_indices(&mut migrated);
+        assert_eq!(migrated.layout.as_deref(), Some("B"));
+        assert_eq!(migrated.style.as_deref(), Some("Dark"));
+        assert_eq!(migrated.layout_index, None);
+        assert_eq!(migrated.style_index, None);
+
+        let mut migrated = settings(1, 2);
+        migrate_indices(&mut migrated);
+        assert_eq!(migrated.style.as_deref(), Some("global/Plain"));
+
+        let mut migrated = settings(1, 0);
+        migrate_indices(&mut migrated);
+        assert_eq!(migrated.style, None);
+
+        let mut migrated = settings(5, 0);
+        migrate_indices(&mut migrated);
+        assert_eq!(migrated.layout, None);
+        assert_eq!(migrated.style, None);
+    }
+}
@@ -0,0 +1,109 @@
+//! Crash recovery for unsaved edits.
+//!
+//! While there are unsaved changes, the app stages a [`Snapshot`] after every edit, undo and redo,
+//! and writes it to the recovery directory periodically. If the app panics, the staged snapshot is
+//! written one last time, so that no edits are lost, and it's offered for restoring on the next
+//! startup.
+
+use crate::{message::Change, nuhxboard::*, types::StyleChoice};
+use nuhxboard_types::{layout::Layout, style::Style};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
+use tracing::{error, info, warn};
+
+pub static RECOVERY_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| KEYBOARDS_PATH.parent().unwrap().join("recovery"));
+
+/// How often staged snapshots are written to disk, in seconds.
+pub const AUTOSAVE_INTERVAL: u64 = 30;
+
+static STAGED: Mutex<Option<Snapshot>> = Mutex::new(None);
+
+/// Unsaved edits to a keyboard, along with the undo history that led to them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub category: String,
+    /// The name of the layout being edited, if it was loaded from the keyboards directory.
+    pub layout_name: Option<String>,
+    pub style_choice: Option<StyleChoice>,
+    pub layout: Layout,
+    pub style: Style,
+    pub edit_history: Vec<Change>,
+    pub history_depth: usize,
+}
+
+/// Returns the path of the snapshot. Each settings profile has its own, so that instances don't
+/// restore each other's edits.
+fn snapshot_path() -> PathBuf {
+    match SETTINGS_PROFILE.get() {
+        Some(profile) => RECOVERY_PATH.join(format!("snapshot-{profile}.json")),
+        None => RECOVERY_PATH.join("snapshot.json"),
+    }
+}
+
+/// Replaces the snapshot that will be written on the next autosave or panic.
+pub fn stage(snapshot: Snapshot) {
+    if let Ok(mut staged) = STAGED.lock() {
+        *staged = Some(snapshot);
+    }
+}
+
+/// Writes the staged snapshot, if there is one, to the recovery directory.
+pub fn flush() -> std::io::Result<()> {
+    // Panics can happen while the snapshot is being staged, so this must not block
+    let Ok(staged) = STAGED.try_lock() else {
+        return Ok(());
+    };
+    let Some(snapshot) = staged.as_ref() else {
+        return Ok(());
+    };
+    let contents = serde_json::to_vec(snapshot)?;
+    write_file(&snapshot_path(), &contents, 0)
+}
+
+/// Discards the staged snapshot and the one on disk, as there are no unsaved edits left to
+/// recover.
+pub fn clear() {
+    if let Ok(mut staged) = STAGED.lock() {
+        *staged = None;
+    }
+    let path = snapshot_path();
+    if path.exists()
+        && let Err(e) = fs::remove_file(&path)
+    {
+        warn!(?e, "Failed to remove recovery snapshot");
+    }
+}
+
+/// Reads the snapshot left behind by a previous run, if there is one. A snapshot that can't be
+/// read is discarded.
+pub fn load() -> Option<Snapshot> {
+    let path = snapshot_path();
+    let contents = fs::read(&path).ok()?;
+    match serde_json::from_slice(&contents) {
+        Ok(snapshot) => {
+            info!("Found recovery snapshot");
+            Some(snapshot)
+        }
+        Err(e) => {
+            warn!(?e, "Discarding unreadable recovery snapshot");
+            let _ = fs::remove_file(&path);
+            None
+        }
+    }
+}
+
+/// Writes the staged snapshot before running the existing panic hook.
+pub fn install_panic_hook() {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Err(e) = flush() {
+            error!(?e, "Failed to write recovery snapshot");
+        }
+        hook(info);
+    }));
+}
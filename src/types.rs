@@ -1,4 +1,6 @@
-use std::{collections::HashMap, sync::Arc};
+use crate::nuhxboard::KEYBOARDS_PATH;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 #[derive(Default, Clone)]
 pub struct SelectionLists {
@@ -11,10 +13,6 @@ pub struct NumberInput {
     pub boundary_x: HashMap<usize, f32>,
     pub boundary_y: HashMap<usize, f32>,
     pub keycode: HashMap<usize, u32>,
-    pub rectangle_position_x: HashMap<usize, f32>,
-    pub rectangle_position_y: HashMap<usize, f32>,
-    pub rectangle_size_x: HashMap<usize, f32>,
-    pub rectangle_size_y: HashMap<usize, f32>,
 }
 
 #[derive(Debug, Clone)]
@@ -22,10 +20,6 @@ pub enum NumberInputType {
     BoundaryX(usize, f32),
     BoundaryY(usize, f32),
     Keycode(usize, u32),
-    RectanglePositionX(usize, f32),
-    RectanglePositionY(usize, f32),
-    RectangleSizeX(usize, f32),
-    RectangleSizeY(usize, f32),
 }
 
 #[derive(Default)]
@@ -113,7 +107,7 @@ impl ColorPickers {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColorPicker {
     KeyboardBackground,
     DefaultMouseSpeedIndicator1,
@@ -134,7 +128,7 @@ pub enum ColorPicker {
     MouseSpeedIndicator2(u32),
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum StyleChoice {
     Default,
     Global(String),
@@ -152,6 +146,25 @@ impl StyleChoice {
             _ => self.to_string(),
         }
     }
+
+    /// The style as saved in [`Settings::style`](nuhxboard_types::settings::Settings::style).
+    pub fn setting(&self) -> Option<String> {
+        match self {
+            Self::Default => None,
+            Self::Global(name) => Some(format!("global/{name}")),
+            Self::Custom(name) => Some(name.clone()),
+        }
+    }
+
+    pub fn from_setting(setting: Option<&str>) -> Self {
+        match setting {
+            None => Self::Default,
+            Some(setting) => match setting.strip_prefix("global/") {
+                Some(name) => Self::Global(name.to_owned()),
+                None => Self::Custom(setting.to_owned()),
+            },
+        }
+    }
 }
 
 impl std::fmt::Display for StyleChoice {
@@ -164,6 +177,78 @@ impl std::fmt::Display for StyleChoice {
     }
 }
 
+/// A category, keyboard or style in the keyboards directory, as managed from the load keyboard
+/// window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyboardItem {
+    Category(String),
+    Keyboard {
+        category: String,
+        name: String,
+    },
+    /// A style of the given keyboard, or a global style if `global` is set.
+    Style {
+        category: String,
+        keyboard: String,
+        name: String,
+        global: bool,
+    },
+}
+
+impl KeyboardItem {
+    /// Whether `name` can be used for a category, keyboard or style. `global` and `images` are
+    /// reserved for global styles and background images.
+    pub fn is_valid_name(name: &str) -> bool {
+        !matches!(name, "" | "." | ".." | "global" | "images") && !name.contains(['/', '\\'])
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            KeyboardItem::Category(name)
+            | KeyboardItem::Keyboard { name, .. }
+            | KeyboardItem::Style { name, .. } => name,
+        }
+    }
+
+    /// The same item with a different name.
+    pub fn with_name(&self, new_name: String) -> Self {
+        let mut item = self.clone();
+        match &mut item {
+            KeyboardItem::Category(name)
+            | KeyboardItem::Keyboard { name, .. }
+            | KeyboardItem::Style { name, .. } => *name = new_name,
+        }
+        item
+    }
+
+    pub fn path(&self) -> PathBuf {
+        match self {
+            KeyboardItem::Category(name) => KEYBOARDS_PATH.join(name),
+            KeyboardItem::Keyboard { category, name } => KEYBOARDS_PATH.join(category).join(name),
+            KeyboardItem::Style {
+                category,
+                keyboard,
+                name,
+                global,
+            } => match global {
+                true => KEYBOARDS_PATH.join("global"),
+                false => KEYBOARDS_PATH.join(category).join(keyboard),
+            }
+            .join(format!("{name}.style")),
+        }
+    }
+}
+
+impl std::fmt::Display for KeyboardItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyboardItem::Category(name) => write!(f, "category \"{name}\""),
+            KeyboardItem::Keyboard { name, .. } => write!(f, "keyboard \"{name}\""),
+            KeyboardItem::Style { name, .. } => write!(f, "style \"{name}\""),
+        }
+    }
+}
+
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum NuhxBoardError {
     #[error("Error parsing settings. Using default settings")]
@@ -180,6 +265,24 @@ pub enum NuhxBoardError {
     UnknownKey(rdevin::Key),
     #[error("Unknown button: {0:?}")]
     UnknownButton(rdevin::Button),
+    #[error("Can't combine shapes: {0}")]
+    ShapeOperation(&'static str),
+    #[error("Couldn't find the {0}")]
+    ItemMissing(KeyboardItem),
+    #[error("Error saving {}", .0.display())]
+    Save(PathBuf, #[source] Arc<std::io::Error>),
+    #[error("There's no loaded {0} to save to. Use \"Save As\" instead")]
+    NoSaveTarget(&'static str),
+    #[error("Can't generate a layout: {0}")]
+    Generate(String),
+    #[error("Error managing keyboard files")]
+    KeyboardFiles(#[source] Arc<std::io::Error>),
+    #[error("Couldn't restore unsaved edits, as the {0} they were made to no longer exists")]
+    RecoveryTarget(KeyboardItem),
+    #[error("Error exporting keyboard pack")]
+    PackExport(#[source] Arc<std::io::Error>),
+    #[error("Error importing keyboard pack")]
+    PackImport(#[source] Arc<std::io::Error>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -273,3 +376,18 @@ impl TryFrom<u32> for MouseScroll {
         }
     }
 }
+
+/// Progress through assigning keycodes to elements in sequence.
+#[derive(Debug, Clone)]
+pub struct Learning {
+    /// Indices of the elements to assign, in order.
+    pub order: Vec<usize>,
+    pub position: usize,
+}
+
+impl Learning {
+    /// The element that the next key press is assigned to.
+    pub fn current(&self) -> Option<usize> {
+        self.order.get(self.position).copied()
+    }
+}
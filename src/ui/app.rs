@@ -1,15 +1,15 @@
 use super::{components::*, keyboard::Keyboard, popups::*};
-use crate::{message::*, nuhxboard::*, Args};
+use crate::{message::*, nuhxboard::*, types::*, Args};
 use clap::Parser;
 use iced::{
     widget::{
-        checkbox, column, container, image::Handle, pick_list, radio, row, space::horizontal, text,
-        text_input, Image, Scrollable, Stack,
+        button, checkbox, column, container, image::Handle, pick_list, radio, row,
+        space::horizontal, text, text_input, Image, Scrollable, Stack,
     },
     window, Background, Border, Color, Length, Theme,
 };
 use iced_aw::{number_input, ContextMenu, SelectionList};
-use iced_multi_window::Window;
+use iced_multi_window::{State, Window};
 use nuhxboard_types::settings::*;
 use std::sync::Arc;
 
@@ -18,25 +18,57 @@ static IMAGE: &[u8] = include_bytes!("../../media/NuhxBoard.png");
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LoadKeyboard;
 impl Window<NuhxBoard, Theme, Message> for LoadKeyboard {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
     fn settings(&self) -> window::Settings {
         window::Settings {
             resizable: false,
             size: iced::Size {
-                width: 300.0,
-                height: 250.0,
+                width: 400.0,
+                height: 365.0,
             },
             ..Default::default()
         }
     }
 
-    fn view<'a>(&self, app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(&self, app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
+        let category = (!app.settings.category.is_empty())
+            .then(|| KeyboardItem::Category(app.settings.category.clone()));
+        let keyboard = app.layout_choice.map(|index| KeyboardItem::Keyboard {
+            category: app.settings.category.clone(),
+            name: app.layout_options[index].clone(),
+        });
+        let style = match (app.layout_choice, app.style_options.get(app.style_choice)) {
+            (Some(index), Some(style)) if *style != StyleChoice::Default => {
+                Some(KeyboardItem::Style {
+                    category: app.settings.category.clone(),
+                    keyboard: app.layout_options[index].clone(),
+                    name: style.name(),
+                    global: style.is_global(),
+                })
+            }
+            _ => None,
+        };
         column![
             text("Category:"),
-            pick_list(
-                app.keyboard_category_options.clone(),
-                Some(app.settings.category.clone()),
-                Message::ChangeKeyboardCategory,
-            ),
+            row![
+                pick_list(
+                    app.keyboard_category_options.clone(),
+                    Some(app.settings.category.clone()),
+                    Message::ChangeKeyboardCategory,
+                ),
+                button("New Keyboard...").on_press(Message::Open(Box::new(NewKeyboard))),
+                button("Import Pack...").on_press(Message::Open(Box::new(ImportPack))),
+            ]
+            .spacing(5),
+            item_buttons(category.clone()),
+            export_button(category),
             row![
                 column![
                     text("Keyboard Layout:"),
@@ -48,7 +80,9 @@ impl Window<NuhxBoard, Theme, Message> for LoadKeyboard {
                         iced_aw::style::selection_list::primary,
                         app.layout_choice,
                         iced::Font::default(),
-                    )
+                    ),
+                    item_buttons(keyboard.clone()),
+                    export_button(keyboard),
                 ],
                 column![
                     text("Keyboard Style:"),
@@ -60,7 +94,8 @@ impl Window<NuhxBoard, Theme, Message> for LoadKeyboard {
                         iced_aw::style::selection_list::primary,
                         Some(app.style_choice),
                         iced::Font::default(),
-                    )
+                    ),
+                    item_buttons(style),
                 ],
             ]
         ]
@@ -76,6 +111,31 @@ impl Window<NuhxBoard, Theme, Message> for LoadKeyboard {
     }
 }
 
+/// Rename, duplicate and delete buttons for `item`, disabled if it's `None`.
+fn item_buttons<'a>(item: Option<KeyboardItem>) -> iced::Element<'a, Message, Theme> {
+    let name_item = |duplicate| {
+        item.clone()
+            .map(|item| Message::Open(Box::new(NameItem { item, duplicate })))
+    };
+    row![
+        button("Rename").on_press_maybe(name_item(false)),
+        button("Duplicate").on_press_maybe(name_item(true)),
+        button("Delete").on_press_maybe(
+            item.clone()
+                .map(|item| Message::Open(Box::new(ConfirmDelete(item))))
+        ),
+    ]
+    .spacing(5)
+    .into()
+}
+
+/// Opens the dialog to export `item` as a keyboard pack, disabled if it's `None`.
+fn export_button<'a>(item: Option<KeyboardItem>) -> iced::Element<'a, Message, Theme> {
+    button("Export Pack...")
+        .on_press_maybe(item.map(|item| Message::Open(Box::new(ExportPack(item)))))
+        .into()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Main;
 impl Window<NuhxBoard, Theme, Message> for Main {
@@ -97,22 +157,16 @@ impl Window<NuhxBoard, Theme, Message> for Main {
         }
     }
 
-    fn view<'a>(&self, app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(&self, app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
         let keyboard = Keyboard::new(app.layout.width, app.layout.height, app);
 
         let context_menu = ContextMenu::new(keyboard, || {
             let mut menu = vec![
                 context_menu_button("Settings")
-                    .on_press_maybe(
-                        (!app.windows.any_of(&SettingsWindow))
-                            .then_some(Message::Open(Box::new(SettingsWindow))),
-                    )
+                    .on_press(Message::Open(Box::new(SettingsWindow)))
                     .into(),
                 context_menu_button("Load Keyboard")
-                    .on_press_maybe(
-                        (!app.windows.any_of(&LoadKeyboard))
-                            .then_some(Message::Open(Box::new(LoadKeyboard))),
-                    )
+                    .on_press(Message::Open(Box::new(LoadKeyboard)))
                     .into(),
                 seperator().into(),
                 context_menu_button(match app.edit_mode {
@@ -141,6 +195,17 @@ impl Window<NuhxBoard, Theme, Message> for Main {
                         .size(15)
                         .into(),
                 );
+                let undo_depth = app.edit_history.len() - app.history_depth;
+                menu.push(
+                    context_menu_button(format!("Undo ({undo_depth})"))
+                        .on_press_maybe((undo_depth > 0).then_some(Message::Undo))
+                        .into(),
+                );
+                menu.push(
+                    context_menu_button(format!("Redo ({})", app.history_depth))
+                        .on_press_maybe((app.history_depth > 0).then_some(Message::Redo))
+                        .into(),
+                );
                 if app.hovered_element.is_some() {
                     menu.push(
                         context_menu_button("Remove Element")
@@ -169,52 +234,148 @@ impl Window<NuhxBoard, Theme, Message> for Main {
                             .into(),
                     );
                 }
+                menu.push(seperator().into());
+                if let Some(learning) = &app.learning {
+                    menu.push(
+                        context_menu_button("Skip Key")
+                            .on_press(Message::SkipLearning)
+                            .into(),
+                    );
+                    menu.push(
+                        context_menu_button(format!(
+                            "Stop Learning ({}/{})",
+                            learning.position,
+                            learning.order.len()
+                        ))
+                        .on_press(Message::StopLearning)
+                        .into(),
+                    );
+                } else {
+                    menu.push(
+                        context_menu_button("Learn Keycodes")
+                            .on_press(Message::StartLearning)
+                            .into(),
+                    );
+                }
+                menu.push(
+                    context_menu_button("Label Keys...")
+                        .on_press(Message::Open(Box::new(LabelKeys)))
+                        .into(),
+                );
+                let has_targets =
+                    !app.selected_elements.is_empty() || app.hovered_element.is_some();
+                menu.append(&mut vec![
+                    seperator().into(),
+                    context_menu_button("Copy")
+                        .on_press_maybe(has_targets.then_some(Message::Copy))
+                        .into(),
+                    context_menu_button("Cut")
+                        .on_press_maybe(has_targets.then_some(Message::Cut))
+                        .into(),
+                    context_menu_button("Paste")
+                        .on_press(Message::Paste(Some(app.right_click_pos)))
+                        .into(),
+                    context_menu_button("Duplicate")
+                        .on_press_maybe(has_targets.then_some(Message::Duplicate))
+                        .into(),
+                ]);
+                if has_targets {
+                    menu.push(seperator().into());
+                    for (label, transform) in [
+                        ("Rotate 90° Clockwise", ElementTransform::Rotate(90.0)),
+                        (
+                            "Rotate 90° Counterclockwise",
+                            ElementTransform::Rotate(-90.0),
+                        ),
+                        ("Flip Horizontally", ElementTransform::FlipHorizontally),
+                        ("Flip Vertically", ElementTransform::FlipVertically),
+                    ] {
+                        menu.push(
+                            context_menu_button(label)
+                                .on_press(Message::TransformElements {
+                                    transform,
+                                    pivot: None,
+                                })
+                                .into(),
+                        );
+                    }
+                    menu.push(
+                        context_menu_button("Transform...")
+                            .on_press(Message::Open(Box::new(TransformDialog)))
+                            .into(),
+                    );
+                }
                 menu.append(&mut vec![
                     seperator().into(),
                     context_menu_button("Keyboard Properties")
-                        .on_press_maybe(
-                            (!app.windows.any_of(&KeyboardProperties))
-                                .then_some(Message::Open(Box::new(KeyboardProperties))),
-                        )
+                        .on_press(Message::Open(Box::new(KeyboardProperties)))
                         .into(),
                     context_menu_button("Element Properties")
-                        .on_press_maybe(if let Some(index) = app.hovered_element {
-                            let window = ElementProperties { index };
-                            (!app.windows.any_of(&window))
-                                .then_some(Message::Open(Box::new(window)))
-                        } else {
-                            None
-                        })
-                        .into(),
-                    context_menu_button("Keyboard Style")
                         .on_press_maybe(
-                            (!app.windows.any_of(&KeyboardStyle))
-                                .then_some(Message::Open(Box::new(KeyboardStyle))),
+                            app.hovered_element
+                                .map(|index| Message::Open(Box::new(ElementProperties { index }))),
                         )
                         .into(),
+                    context_menu_button("Keyboard Style")
+                        .on_press(Message::Open(Box::new(KeyboardStyle)))
+                        .into(),
                     context_menu_button("Element Style")
-                        .on_press_maybe(if let Some(index) = app.hovered_element {
+                        .on_press_maybe(app.hovered_element.map(|index| {
                             let id = app.layout.elements[index].id();
-                            let window = ElementStyle { id };
-                            (!app.windows.any_of(&window))
-                                .then_some(Message::Open(Box::new(window)))
-                        } else {
-                            None
-                        })
+                            Message::Open(Box::new(ElementStyle { id }))
+                        }))
+                        .into(),
+                    context_menu_button("Keyboard Shortcuts")
+                        .on_press(Message::Open(Box::new(KeyboardShortcuts)))
                         .into(),
                 ]);
+                if app.selected_elements.len() > 1 {
+                    menu.push(seperator().into());
+                    for (label, align) in [
+                        ("Align Left", Align::Left),
+                        ("Align Right", Align::Right),
+                        ("Align Top", Align::Top),
+                        ("Align Bottom", Align::Bottom),
+                        ("Center Horizontally", Align::CenterHorizontally),
+                        ("Center Vertically", Align::CenterVertically),
+                        ("Distribute Horizontally", Align::DistributeHorizontally),
+                        ("Distribute Vertically", Align::DistributeVertically),
+                    ] {
+                        menu.push(
+                            context_menu_button(label)
+                                .on_press(Message::Align(align))
+                                .into(),
+                        );
+                    }
+                    menu.push(seperator().into());
+                    for (label, operation) in [
+                        ("Union Shapes", ShapeOperation::Union),
+                        ("Subtract Others From This Shape", ShapeOperation::Subtract),
+                        ("Intersect Shapes", ShapeOperation::Intersect),
+                    ] {
+                        menu.push(
+                            context_menu_button(label)
+                                .on_press(Message::CombineShapes(operation))
+                                .into(),
+                        );
+                    }
+                }
             }
 
+            // Deleting or renaming the loaded keyboard leaves its layout loaded, but with nowhere
+            // to save it other than "Save As"
+            let loaded = app.layout_choice.is_some();
+            let style_loaded = loaded && app.style_options.get(app.style_choice).is_some();
             menu.append(&mut vec![
                 seperator().into(),
                 context_menu_button("Save Definition")
-                    .on_press(Message::SaveLayout(None))
+                    .on_press_maybe(loaded.then_some(Message::SaveLayout(None)))
                     .into(),
                 context_menu_button("Save Definition As...")
                     .on_press(Message::Open(Box::new(SaveDefinitionAs)))
                     .into(),
                 context_menu_button("Save Style")
-                    .on_press(Message::SaveStyle(None))
+                    .on_press_maybe(style_loaded.then_some(Message::SaveStyle(None)))
                     .into(),
                 context_menu_button("Save Style As...")
                     .on_press(Message::Open(Box::new(SaveStyleAs)))
@@ -233,9 +394,7 @@ impl Window<NuhxBoard, Theme, Message> for Main {
                 .into()
         });
         if app.style.background_image_file_name.is_some() {
-            let image = Image::new(Handle::from_path(
-                KEYBOARDS_PATH.parent().unwrap().join("background.png"),
-            ));
+            let image = Image::new(Handle::from_path(background_path()));
             return Stack::with_children(vec![image.into(), context_menu.into()]).into();
         }
         context_menu.into()
@@ -260,18 +419,26 @@ impl Window<NuhxBoard, Theme, Message> for Main {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SettingsWindow;
 impl Window<NuhxBoard, Theme, Message> for SettingsWindow {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
     fn settings(&self) -> window::Settings {
         window::Settings {
             resizable: false,
             size: iced::Size {
                 width: 420.0,
-                height: 300.0,
+                height: 410.0,
             },
             ..Default::default()
         }
     }
 
-    fn view<'a>(&self, app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(&self, app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
         let display_choices = app
             .display_options
             .iter()
@@ -399,6 +566,28 @@ impl Window<NuhxBoard, Theme, Message> for SettingsWindow {
             ]
         ];
 
+        let snapping = column![
+            text("Snapping in the editor (hold Alt to bypass)").size(12),
+            row![
+                checkbox("Snap to grid", app.settings.snapping.grid)
+                    .text_size(12)
+                    .size(15)
+                    .on_toggle(|_| Message::ChangeSetting(Setting::SnapToGrid)),
+                horizontal(),
+                text("Grid cells per key: ").size(12),
+                number_input(&app.settings.snapping.subdivisions, 1.., |v| {
+                    Message::ChangeSetting(Setting::GridSubdivisions(v))
+                })
+                .set_size(12.0)
+            ]
+            .align_y(iced::Alignment::Center),
+            checkbox("Snap to other elements", app.settings.snapping.elements)
+                .text_size(12)
+                .size(15)
+                .on_toggle(|_| Message::ChangeSetting(Setting::SnapToElements)),
+        ]
+        .padding(5);
+
         column![
             text(format!("NuhxBoard v{}", env!("CARGO_PKG_VERSION"))).size(20),
             input,
@@ -409,7 +598,17 @@ impl Window<NuhxBoard, Theme, Message> for SettingsWindow {
                     .on_input(|v| Message::ChangeSetting(Setting::WindowTitle(v)))
             ]
             .align_y(iced::Alignment::Center),
+            row![
+                text("Backups to keep when saving: ").size(12),
+                number_input(&app.settings.backups, 0.., |v| {
+                    Message::ChangeSetting(Setting::Backups(v))
+                })
+                .set_size(12.0)
+            ]
+            .padding(5)
+            .align_y(iced::Alignment::Center),
             capitalization,
+            snapping,
         ]
         .align_x(iced::Alignment::Center)
         .into()
@@ -45,7 +45,7 @@ pub fn picker_button<'a>(
     .align_y(Alignment::Center)
 }
 
-pub fn context_menu_button(label: &str) -> Button<'_, Message> {
+pub fn context_menu_button<'a>(label: impl IntoFragment<'a>) -> Button<'a, Message> {
     let text = text(label).size(12);
     button(text)
         .style(|theme, status| {
@@ -1,21 +1,27 @@
-use std::{collections::HashSet, ops::Deref};
+use std::{
+    collections::{BTreeSet, HashSet},
+    ops::Deref,
+};
 
 use colorgrad::Gradient;
-use geo::{BoundingRect, Coord, Distance, Euclidean, LineString, Polygon, Within};
+use geo::{
+    BoundingRect, Closest, ClosestPoint, Coord, Distance, Euclidean, Intersects, LineString,
+    Polygon, Rect, Within,
+};
 use iced::{
     advanced::{layout::Node, widget::tree, Renderer as _, Shell, Widget},
-    mouse,
+    keyboard, mouse,
     widget::{
         canvas::{self, Geometry},
         image::Handle,
     },
-    Color, Element, Event, Length, Rectangle, Renderer, Size,
+    Color, Element, Event, Length, Rectangle, Renderer, Size, Transformation,
 };
 use iced_graphics::geometry::{Image, Path, Renderer as _};
 use image::ImageReader;
 use nalgebra::{Vector2, Vector3};
 use nuhxboard_types::{
-    layout::{BoardElement, CommonDefinitionRef},
+    layout::{group_bounding_rect, BoardElement, CommonDefinitionRef},
     settings::Capitalization,
 };
 use rdevin::keycodes::windows::code_from_key as win_keycode_from_key;
@@ -23,14 +29,21 @@ use tracing::{debug, trace};
 
 use crate::{
     message::{Change, Message},
-    nuhxboard::{NuhxBoard, KEYBOARDS_PATH},
+    nuhxboard::{NuhxBoard, DEFAULT_KEY_SIZE, KEYBOARDS_PATH},
+    types::Learning,
 };
 
-const BALL_TO_RADIUS_RATIO: f32 = 0.2;
+pub const BALL_TO_RADIUS_RATIO: f32 = 0.2;
 const HOVER_EDGE_DISTANCE: f32 = 6.0;
 const HOVER_FACE_THICKNESS: f32 = 4.0;
 const HOVER_VERTEX_THICKNESS: f32 = 6.0;
 const MOUSE_SPEED_MAGNITUDE_DOWNSCALE: f32 = 0.000005;
+/// How close an edge or center has to be to another element's to snap to it.
+const SNAP_DISTANCE: f32 = 5.0;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.0;
+/// How much one line of scrolling zooms in or out by.
+const ZOOM_STEP: f32 = 1.1;
 
 pub struct Keyboard<'a> {
     app: &'a NuhxBoard,
@@ -49,12 +62,24 @@ impl Deref for Keyboard<'_> {
 #[derive(Default)]
 struct State {
     held_element: Option<usize>,
+    /// Elements that move along with the held element.
+    dragged_elements: Vec<usize>,
     hovered_face: Option<usize>,
     hovered_vertex: Option<usize>,
-    selected_element: Option<usize>,
     interaction: Interaction,
     previous_cursor_position: Coord<f32>,
     delta_accumulator: Coord<f32>,
+    /// How far the dragged elements or vertex have actually been moved, after snapping.
+    snapped_delta: Coord<f32>,
+    /// Bounds of whatever is being dragged, from before the drag began. Only set for drags that
+    /// snap.
+    drag_origin: Option<Rect<f32>>,
+    /// `(x, y)` of the vertical and horizontal guides that the drag is snapped to.
+    guides: (Option<f32>, Option<f32>),
+    modifiers: keyboard::Modifiers,
+    space_held: bool,
+    /// Used to detect double clicks.
+    last_click: Option<mouse::Click>,
 }
 
 impl State {
@@ -85,11 +110,19 @@ impl State {
     }
 }
 
-#[derive(Default, Debug, PartialEq, Eq)]
+#[derive(Default, Debug, PartialEq)]
 enum Interaction {
     #[default]
     None,
     Dragging,
+    /// Dragging out a selection rectangle from `start`.
+    Selecting {
+        start: Coord<f32>,
+    },
+    /// Panning the view, last seen at `last` in window coordinates.
+    Panning {
+        last: iced::Point,
+    },
 }
 
 impl<'a> Keyboard<'a> {
@@ -181,7 +214,7 @@ impl<'a> Keyboard<'a> {
 
                     if self.hovered_element == Some(index)
                         && state.held_element.is_none()
-                        && state.selected_element.is_none()
+                        && self.selected_elements.is_empty()
                     {
                         frame.fill(&outer, Color::from_rgba(0.0, 0.0, 1.0, 0.5));
                         if state.hovered_face.is_some() {
@@ -197,7 +230,7 @@ impl<'a> Keyboard<'a> {
                     } else if state.held_element == Some(index) {
                         frame.fill(&outer, Color::from_rgba(1.0, 1.0, 1.0, 0.5));
                     }
-                    if state.selected_element == Some(index) && state.hovered_face.is_some() {
+                    if self.selected_elements.contains(&index) && state.hovered_face.is_some() {
                         frame.stroke(
                             &outer,
                             canvas::Stroke {
@@ -212,7 +245,7 @@ impl<'a> Keyboard<'a> {
                             },
                         )
                     }
-                    if state.selected_element == Some(index) {
+                    if self.selected_elements.contains(&index) {
                         frame.stroke(
                             &outer,
                             canvas::Stroke {
@@ -320,12 +353,18 @@ impl<'a> Keyboard<'a> {
                 .unwrap_or(&self.style.default_key_style.loose)
         };
 
+        // Vertices and faces are edited on the boundaries, but rounded corners and ellipses are
+        // drawn from the outline
         let shape = Polygon::new(LineString::from(def.boundaries.clone()), vec![]);
+        let outline = def.outline();
 
         let key = Path::new(|builder| {
-            builder.move_to(def.boundaries[0].clone().into());
-            for boundary in def.boundaries.iter().skip(1) {
-                builder.line_to(boundary.clone().into());
+            let mut points = outline.exterior().points();
+            if let Some(first) = points.next() {
+                builder.move_to(iced::Point::new(first.x(), first.y()));
+            }
+            for point in points {
+                builder.line_to(iced::Point::new(point.x(), point.y()));
             }
             builder.close();
         });
@@ -356,7 +395,7 @@ impl<'a> Keyboard<'a> {
                 );
             }
 
-            if state.selected_element == Some(index) {
+            if self.selected_elements.contains(&index) {
                 frame.stroke(
                     &key,
                     canvas::Stroke {
@@ -381,7 +420,7 @@ impl<'a> Keyboard<'a> {
                             y: face.end.y,
                         },
                     );
-                    if state.selected_element == Some(index) {
+                    if self.selected_elements.contains(&index) {
                         frame.stroke(
                             &path,
                             canvas::Stroke {
@@ -415,7 +454,7 @@ impl<'a> Keyboard<'a> {
                         },
                         iced::Size::new(HOVER_VERTEX_THICKNESS, HOVER_VERTEX_THICKNESS),
                     );
-                    if state.selected_element == Some(index) {
+                    if self.selected_elements.contains(&index) {
                         frame.fill(
                             &path,
                             canvas::Fill {
@@ -480,9 +519,15 @@ impl<'a> Keyboard<'a> {
                 frame.fill(&key, Color::from(current_style.background));
             }
 
-            if self.hovered_element == Some(index)
+            if self.learning.as_ref().and_then(Learning::current) == Some(index) {
+                // #32CD32
+                frame.fill(
+                    &key,
+                    Color::from_rgba(50.0 / 255.0, 205.0 / 255.0, 50.0 / 255.0, 0.5),
+                );
+            } else if self.hovered_element == Some(index)
                 && state.held_element.is_none()
-                && state.selected_element.is_none()
+                && self.selected_elements.is_empty()
             {
                 frame.fill(&key, Color::from_rgba(0.0, 0.0, 1.0, 0.5));
             } else if state.held_element == Some(index) {
@@ -503,6 +548,96 @@ impl<'a> Keyboard<'a> {
         (fg, bg)
     }
 
+    /// Draws the layout in layout coordinates.
+    fn draw_layout(&self, state: &State, renderer: &mut Renderer) {
+        let mut fgs = Vec::new();
+        let mut bgs = Vec::new();
+
+        for i in 0..self.caches.len() {
+            if state.held_element == Some(i) {
+                continue;
+            }
+            let (fg, bg) = self.draw_element(state, renderer, i);
+            fgs.push(fg);
+            bgs.push(bg);
+        }
+
+        renderer.with_layer(self.bounds(), |renderer| {
+            for geo in bgs {
+                renderer.draw_geometry(geo);
+            }
+        });
+
+        renderer.with_layer(self.bounds(), |renderer| {
+            for geo in fgs {
+                renderer.draw_geometry(geo);
+            }
+        });
+
+        if let Some(i) = state
+            .held_element
+            .or(self.selected_elements.first().copied())
+        {
+            let (fg, bg) = self.draw_element(state, renderer, i);
+            renderer.with_layer(self.bounds(), |renderer| {
+                renderer.draw_geometry(bg);
+            });
+
+            renderer.with_layer(self.bounds(), |renderer| {
+                renderer.draw_geometry(fg);
+            });
+        }
+
+        if state.guides != (None, None) {
+            let mut frame = canvas::Frame::new(renderer, self.bounds().size());
+            let stroke = canvas::Stroke {
+                // #FF4500
+                style: canvas::Style::Solid(Color::from_rgb(1.0, 45.0 / 255.0, 0.0)),
+                width: 1.0,
+                ..Default::default()
+            };
+            if let Some(x) = state.guides.0 {
+                frame.stroke(
+                    &Path::line(iced::Point { x, y: 0.0 }, iced::Point { x, y: self.height }),
+                    stroke,
+                );
+            }
+            if let Some(y) = state.guides.1 {
+                frame.stroke(
+                    &Path::line(iced::Point { x: 0.0, y }, iced::Point { x: self.width, y }),
+                    stroke,
+                );
+            }
+            renderer.with_layer(self.bounds(), |renderer| {
+                renderer.draw_geometry(frame.into_geometry());
+            });
+        }
+
+        if let Interaction::Selecting { start } = state.interaction {
+            let band = Rect::new(start, state.previous_cursor_position);
+            let mut frame = canvas::Frame::new(renderer, self.bounds().size());
+            let path = Path::rectangle(
+                iced::Point {
+                    x: band.min().x,
+                    y: band.min().y,
+                },
+                iced::Size::new(band.width(), band.height()),
+            );
+            frame.fill(&path, Color::from_rgba(0.0, 0.0, 1.0, 0.2));
+            frame.stroke(
+                &path,
+                canvas::Stroke {
+                    style: canvas::Style::Solid(Color::from_rgb(0.0, 0.0, 1.0)),
+                    width: 1.0,
+                    ..Default::default()
+                },
+            );
+            renderer.with_layer(self.bounds(), |renderer| {
+                renderer.draw_geometry(frame.into_geometry());
+            });
+        }
+    }
+
     fn bounds(&self) -> Rectangle {
         Rectangle::with_size(Size::new(self.width, self.height))
     }
@@ -540,6 +675,78 @@ impl<'a> Keyboard<'a> {
         }
     }
 
+    /// Returns how far to move whatever is being dragged, snapping `state.delta_accumulator` to
+    /// the grid and to the edges and centers of other elements. Updates `state.guides` to the
+    /// lines snapped to.
+    fn snap(&self, state: &mut State) -> Coord<f32> {
+        let mut delta = state.delta_accumulator;
+        state.guides = (None, None);
+        let Some(origin) = state.drag_origin else {
+            return delta;
+        };
+        if state.modifiers.alt() {
+            return delta;
+        }
+
+        let snapping = &self.settings.snapping;
+        let moved = Rect::new(origin.min() + delta, origin.max() + delta);
+
+        if snapping.grid && snapping.subdivisions > 0 {
+            let cell = DEFAULT_KEY_SIZE / snapping.subdivisions as f32;
+            delta.x += (moved.min().x / cell).round() * cell - moved.min().x;
+            delta.y += (moved.min().y / cell).round() * cell - moved.min().y;
+        }
+
+        if snapping.elements {
+            let targets: Vec<Rect<f32>> = self
+                .layout
+                .elements
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| !state.dragged_elements.contains(index))
+                .map(|(_, element)| element.bounding_rect())
+                .collect();
+            let lines = |rect: &Rect<f32>| {
+                (
+                    [rect.min().x, rect.center().x, rect.max().x],
+                    [rect.min().y, rect.center().y, rect.max().y],
+                )
+            };
+            let (moved_x, moved_y) = lines(&moved);
+            // `(distance, target line, adjustment)`
+            let mut best_x: Option<(f32, f32, f32)> = None;
+            let mut best_y: Option<(f32, f32, f32)> = None;
+            for target in &targets {
+                let (target_x, target_y) = lines(target);
+                for (moving, target, best) in [
+                    (&moved_x, &target_x, &mut best_x),
+                    (&moved_y, &target_y, &mut best_y),
+                ] {
+                    for m in moving {
+                        for t in target {
+                            let distance = (t - m).abs();
+                            if distance <= SNAP_DISTANCE / self.zoom
+                                && best.is_none_or(|(best, _, _)| distance < best)
+                            {
+                                *best = Some((distance, *t, t - m));
+                            }
+                        }
+                    }
+                }
+            }
+            if let Some((_, line, adjustment)) = best_x {
+                delta.x = state.delta_accumulator.x + adjustment;
+                state.guides.0 = Some(line);
+            }
+            if let Some((_, line, adjustment)) = best_y {
+                delta.y = state.delta_accumulator.y + adjustment;
+                state.guides.1 = Some(line);
+            }
+        }
+
+        delta
+    }
+
     fn no_interaction(
         &self,
         state: &mut State,
@@ -547,37 +754,48 @@ impl<'a> Keyboard<'a> {
         shell: &mut Shell<'_, Message>,
     ) {
         state.previous_cursor_position = cursor_position;
+        // Hover distances are in window pixels, regardless of zoom
+        let edge_distance = HOVER_EDGE_DISTANCE / self.zoom;
         for (index, element) in self.layout.elements.iter().enumerate() {
             match CommonDefinitionRef::try_from(element) {
                 Ok(def) => {
                     let bounds = Polygon::new(LineString::from(def.boundaries.clone()), vec![]);
-
-                    if cursor_position.is_within(&bounds)
-                        && (state.selected_element == Some(index)
-                            || state.selected_element.is_none())
+                    // Faces and vertices can only be edited one element at a time
+                    let editable = self.selected_elements.is_empty()
+                        || (self.selected_elements.len() == 1
+                            && self.selected_elements.contains(&index));
+                    let exterior = bounds.exterior();
+
+                    // The boundaries of rounded and elliptical keys lie outside of what's drawn,
+                    // but must still be reachable
+                    if cursor_position.is_within(&def.outline())
+                        || (editable
+                            && Euclidean.distance(&geo::Point::from(cursor_position), exterior)
+                                <= edge_distance)
                     {
-                        let exterior = bounds.exterior();
-                        if !(exterior.coords().enumerate().any(|(i, vertex)| {
-                            if Euclidean.distance(cursor_position, *vertex) <= HOVER_EDGE_DISTANCE {
-                                if state.set_hovered_vertex(i) {
-                                    debug!(index, vertex = i, "Setting hovered vertex");
-                                    shell.publish(Message::ClearCache(index));
+                        if !editable
+                            || !(exterior.coords().enumerate().any(|(i, vertex)| {
+                                if Euclidean.distance(cursor_position, *vertex) <= edge_distance {
+                                    if state.set_hovered_vertex(i) {
+                                        debug!(index, vertex = i, "Setting hovered vertex");
+                                        shell.publish(Message::ClearCache(index));
+                                    }
+                                    true
+                                } else {
+                                    false
                                 }
-                                true
-                            } else {
-                                false
-                            }
-                        }) || exterior.lines().enumerate().any(|(i, face)| {
-                            if Euclidean.distance(cursor_position, &face) <= HOVER_EDGE_DISTANCE {
-                                if state.set_hovered_face(i) {
-                                    debug!(index, face = i, "Setting hovered face");
-                                    shell.publish(Message::ClearCache(index));
+                            }) || exterior.lines().enumerate().any(|(i, face)| {
+                                if Euclidean.distance(cursor_position, &face) <= edge_distance {
+                                    if state.set_hovered_face(i) {
+                                        debug!(index, face = i, "Setting hovered face");
+                                        shell.publish(Message::ClearCache(index));
+                                    }
+                                    true
+                                } else {
+                                    false
                                 }
-                                true
-                            } else {
-                                false
-                            }
-                        })) {
+                            }))
+                        {
                             let mut clear = false;
                             if state.hovered_face.is_some() {
                                 debug!(index, "Clearing hovered face");
@@ -607,11 +825,9 @@ impl<'a> Keyboard<'a> {
                     {
                         shell.publish(Message::UpdateHoveredElement(Some(index)));
                     }
-                    if ((def.radius - HOVER_EDGE_DISTANCE)..(def.radius + HOVER_EDGE_DISTANCE))
-                        .contains(
-                            &Euclidean.distance(cursor_position, Coord::from(def.location.clone())),
-                        )
-                    {
+                    if ((def.radius - edge_distance)..(def.radius + edge_distance)).contains(
+                        &Euclidean.distance(cursor_position, Coord::from(def.location.clone())),
+                    ) {
                         if state.hovered_face.is_none() {
                             debug!(index, "Hovering mouse speed indicator edge");
                             state.hovered_face = Some(0);
@@ -664,40 +880,13 @@ impl<Theme> Widget<Message, Theme, Renderer> for Keyboard<'_> {
     ) {
         let state = tree.state.downcast_ref::<State>();
 
-        let mut fgs = Vec::new();
-        let mut bgs = Vec::new();
-
-        for i in 0..self.caches.len() {
-            if state.held_element == Some(i) {
-                continue;
-            }
-            let (fg, bg) = self.draw_element(state, renderer, i);
-            fgs.push(fg);
-            bgs.push(bg);
-        }
-
         renderer.with_layer(self.bounds(), |renderer| {
-            for geo in bgs {
-                renderer.draw_geometry(geo);
-            }
+            renderer.with_transformation(
+                Transformation::translate(self.pan.x, self.pan.y)
+                    * Transformation::scale(self.zoom),
+                |renderer| self.draw_layout(state, renderer),
+            );
         });
-
-        renderer.with_layer(self.bounds(), |renderer| {
-            for geo in fgs {
-                renderer.draw_geometry(geo);
-            }
-        });
-
-        if let Some(i) = state.held_element.or(state.selected_element) {
-            let (fg, bg) = self.draw_element(state, renderer, i);
-            renderer.with_layer(self.bounds(), |renderer| {
-                renderer.draw_geometry(bg);
-            });
-
-            renderer.with_layer(self.bounds(), |renderer| {
-                renderer.draw_geometry(fg);
-            });
-        }
     }
 
     fn update(
@@ -711,31 +900,109 @@ impl<Theme> Widget<Message, Theme, Renderer> for Keyboard<'_> {
         shell: &mut iced::advanced::Shell<'_, Message>,
         _viewport: &Rectangle,
     ) {
-        let Event::Mouse(event) = event else {
-            return;
-        };
         let state = state.state.downcast_mut::<State>();
+        let event = match event {
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.modifiers = *modifiers;
+                return;
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Named(keyboard::key::Named::Space),
+                ..
+            }) => {
+                state.space_held = true;
+                return;
+            }
+            Event::Keyboard(keyboard::Event::KeyReleased {
+                key: keyboard::Key::Named(keyboard::key::Named::Space),
+                ..
+            }) => {
+                state.space_held = false;
+                return;
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Named(keyboard::key::Named::Delete),
+                ..
+            }) if self.edit_mode
+                && self.learning.is_none()
+                && state.interaction == Interaction::None =>
+            {
+                // Deleting anything else is handled by the app
+                if let (Some(index), Some(vertex)) = (self.hovered_element, state.hovered_vertex) {
+                    shell.publish(Message::RemoveVertex { index, vertex });
+                    state.hovered_vertex = None;
+                    shell.capture_event();
+                }
+                return;
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Character(character),
+                modifiers,
+                ..
+            }) if self.edit_mode && modifiers.command() && character.as_str() == "0" => {
+                shell.publish(Message::SetView {
+                    zoom: 1.0,
+                    pan: iced::Vector::ZERO,
+                });
+                shell.capture_event();
+                return;
+            }
+            Event::Mouse(event) => event,
+            _ => return,
+        };
         if !self.edit_mode {
             if self.hovered_element.is_some() {
                 shell.publish(Message::UpdateHoveredElement(None));
-            } else if state.selected_element.is_some() {
-                state.selected_element = None;
             } else if state.held_element.is_some() {
                 state.held_element = None;
             };
             return;
         }
 
-        let Some(cursor_position) = cursor.position_in(self.bounds()) else {
+        let Some(window_position) = cursor.position_in(self.bounds()) else {
             return;
         };
 
+        let cursor_position = self.canvas_point(window_position);
         let cursor_position = Coord {
             x: cursor_position.x,
             y: cursor_position.y,
         };
 
         match event {
+            mouse::Event::WheelScrolled { delta } if state.modifiers.command() => {
+                let lines = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => *y,
+                    mouse::ScrollDelta::Pixels { y, .. } => y / DEFAULT_KEY_SIZE,
+                };
+                let zoom = (self.zoom * ZOOM_STEP.powf(lines)).clamp(MIN_ZOOM, MAX_ZOOM);
+                // Keep the point under the cursor in place
+                let anchor = iced::Vector::new(window_position.x, window_position.y);
+                shell.publish(Message::SetView {
+                    zoom,
+                    pan: anchor - (anchor - self.pan) * (zoom / self.zoom),
+                });
+                shell.capture_event();
+            }
+            mouse::Event::ButtonPressed(mouse::Button::Middle)
+                if state.interaction == Interaction::None =>
+            {
+                state.interaction = Interaction::Panning {
+                    last: window_position,
+                };
+            }
+            mouse::Event::ButtonPressed(mouse::Button::Left)
+                if state.space_held && state.interaction == Interaction::None =>
+            {
+                state.interaction = Interaction::Panning {
+                    last: window_position,
+                };
+            }
+            mouse::Event::ButtonReleased(mouse::Button::Middle)
+                if matches!(state.interaction, Interaction::Panning { .. }) =>
+            {
+                state.interaction = Interaction::None;
+            }
             mouse::Event::CursorMoved { .. } => {
                 match state.interaction {
                     Interaction::None => {
@@ -759,86 +1026,201 @@ impl<Theme> Widget<Message, Theme, Renderer> for Keyboard<'_> {
                                         state.previous_cursor_position,
                                     ),
                                 });
-                            } else if let Some(vertex) = state.hovered_vertex {
-                                shell.publish(Message::MoveVertex {
-                                    index,
-                                    vertex,
-                                    delta,
-                                })
                             } else {
-                                shell.publish(Message::MoveElement { index, delta });
+                                let snapped = self.snap(state);
+                                let delta = snapped - state.snapped_delta;
+                                state.snapped_delta = snapped;
+                                if delta == Coord::zero() {
+                                    return;
+                                }
+                                if let Some(vertex) = state.hovered_vertex {
+                                    shell.publish(Message::MoveVertex {
+                                        index,
+                                        vertex,
+                                        delta,
+                                    })
+                                } else {
+                                    shell.publish(Message::MoveElements {
+                                        indices: state.dragged_elements.clone(),
+                                        delta,
+                                    });
+                                }
                             }
                             return;
                         }
                     }
+                    Interaction::Selecting { .. } => {
+                        shell.request_redraw();
+                    }
+                    Interaction::Panning { last } => {
+                        state.interaction = Interaction::Panning {
+                            last: window_position,
+                        };
+                        shell.publish(Message::SetView {
+                            zoom: self.zoom,
+                            pan: self.pan + (window_position - last),
+                        });
+                    }
                 }
                 state.previous_cursor_position = cursor_position;
             }
+            mouse::Event::ButtonPressed(mouse::Button::Left) if self.learning.is_some() => {
+                // Clicking a key while learning chooses it as the next one to assign
+                if let Some(hovered) = self.hovered_element {
+                    shell.publish(Message::LearnElement(hovered));
+                }
+            }
             mouse::Event::ButtonPressed(mouse::Button::Left) => {
-                state.interaction = Interaction::Dragging;
-
-                if state.selected_element.is_none() {
-                    state.held_element = self.hovered_element;
-                    if let Some(i) = state.held_element {
-                        debug!(index = i, "picking up element");
-                        shell.publish(Message::ClearCache(i));
+                let click =
+                    mouse::Click::new(window_position, mouse::Button::Left, state.last_click);
+                state.last_click = Some(click);
+                let Some(hovered) = self.hovered_element else {
+                    state.interaction = Interaction::Selecting {
+                        start: cursor_position,
+                    };
+                    return;
+                };
+
+                if click.kind() == mouse::click::Kind::Double {
+                    if let (Some(face), Ok(def)) = (
+                        state.hovered_face,
+                        CommonDefinitionRef::try_from(&self.layout.elements[hovered]),
+                    ) {
+                        let polygon =
+                            Polygon::new(LineString::from(def.boundaries.clone()), vec![]);
+                        let line = polygon.exterior().lines().nth(face).unwrap();
+                        let position = match line.closest_point(&geo::Point::from(cursor_position))
+                        {
+                            Closest::Intersection(point) | Closest::SinglePoint(point) => point.0,
+                            Closest::Indeterminate => cursor_position,
+                        };
+                        shell.publish(Message::InsertVertex {
+                            index: hovered,
+                            face,
+                            position,
+                        });
+                        state.hovered_face = None;
+                        return;
                     }
-                } else {
-                    state.held_element = state.selected_element;
-                    if let Some(i) = state.selected_element {
-                        debug!(index = i, "picking up element");
-                        shell.publish(Message::ClearCache(i));
+                }
+
+                if state.modifiers.shift() {
+                    let mut selection = self.selected_elements.clone();
+                    if !selection.remove(&hovered) {
+                        selection.insert(hovered);
                     }
-                    state.selected_element = None;
+                    shell.publish(Message::UpdateSelection(selection));
+                    return;
                 }
+
+                state.interaction = Interaction::Dragging;
+                state.held_element = Some(hovered);
+                state.dragged_elements = if self.selected_elements.contains(&hovered) {
+                    self.selected_elements.iter().copied().collect()
+                } else {
+                    vec![hovered]
+                };
+                state.drag_origin = if let Some(vertex) = state.hovered_vertex {
+                    CommonDefinitionRef::try_from(&self.layout.elements[hovered])
+                        .ok()
+                        .map(|def| {
+                            let vertex = Coord::from(def.boundaries[vertex].clone());
+                            Rect::new(vertex, vertex)
+                        })
+                } else if state.hovered_face.is_none() {
+                    group_bounding_rect(
+                        state
+                            .dragged_elements
+                            .iter()
+                            .map(|index| &self.layout.elements[*index]),
+                    )
+                } else {
+                    None
+                };
+                debug!(index = hovered, "picking up element");
+                shell.publish(Message::ClearCache(hovered));
             }
             mouse::Event::ButtonReleased(mouse::Button::Left) => {
-                if state.delta_accumulator != Coord::default() {
-                    if let Some(message) = state.held_element.map(|index| {
-                        if let Some(face) = state.hovered_face {
-                            let delta = self.face_move_delta(
-                                face,
-                                index,
-                                state.delta_accumulator,
-                                cursor_position - state.delta_accumulator,
-                            );
-                            Message::PushChange(Change::MoveFace { index, face, delta })
-                        } else if let Some(vertex) = state.hovered_vertex {
-                            Message::PushChange(Change::MoveVertex {
-                                index,
-                                vertex,
-                                delta: state.delta_accumulator,
-                            })
+                match std::mem::take(&mut state.interaction) {
+                    Interaction::None | Interaction::Panning { .. } => {}
+                    Interaction::Dragging => {
+                        if state.delta_accumulator != Coord::default() {
+                            let change = state.held_element.map(|index| {
+                                if let Some(face) = state.hovered_face {
+                                    let delta = self.face_move_delta(
+                                        face,
+                                        index,
+                                        state.delta_accumulator,
+                                        cursor_position - state.delta_accumulator,
+                                    );
+                                    (Change::MoveFace { index, face, delta }, delta)
+                                } else if let Some(vertex) = state.hovered_vertex {
+                                    let delta = state.snapped_delta;
+                                    (
+                                        Change::MoveVertex {
+                                            index,
+                                            vertex,
+                                            delta,
+                                        },
+                                        delta,
+                                    )
+                                } else {
+                                    let indices = state.dragged_elements.clone();
+                                    let delta = state.snapped_delta;
+                                    (Change::MoveElements { indices, delta }, delta)
+                                }
+                            });
+                            // Snapping can bring whatever was dragged back to where it started
+                            if let Some((change, delta)) = change
+                                && delta != Coord::zero()
+                            {
+                                shell.publish(Message::PushChange(change));
+                            }
+                            state.delta_accumulator = Coord::default();
+                            state.snapped_delta = Coord::default();
+                            if state.hovered_face.is_some() || state.hovered_vertex.is_some() {
+                                shell.publish(Message::UpdateSelection(
+                                    state.held_element.into_iter().collect(),
+                                ));
+                            } else {
+                                shell.publish(Message::UpdateSelection(
+                                    state.dragged_elements.iter().copied().collect(),
+                                ));
+                            }
                         } else {
-                            Message::PushChange(Change::MoveElement {
-                                index,
-                                delta: state.delta_accumulator,
-                            })
+                            shell.publish(Message::UpdateSelection(
+                                self.hovered_element.into_iter().collect(),
+                            ));
                         }
-                    }) {
-                        shell.publish(message);
-                    }
-                    state.delta_accumulator = Coord::default();
-                    state.selected_element = state.held_element;
-                    if let Some(i) = state.selected_element {
-                        debug!(index = i, "selecting element");
-                        shell.publish(Message::ClearCache(i));
+
+                        if let Some(i) = state.held_element {
+                            debug!(index = i, "dropping element");
+                            shell.publish(Message::ClearCache(i));
+                        }
+                        state.held_element = None;
+                        state.dragged_elements.clear();
+                        state.drag_origin = None;
+                        state.guides = (None, None);
                     }
-                } else {
-                    state.selected_element = self.hovered_element;
-                    if let Some(i) = state.selected_element {
-                        debug!(index = i, "selecting element");
-                        shell.publish(Message::ClearCache(i));
+                    Interaction::Selecting { start } => {
+                        let band = Rect::new(start, cursor_position);
+                        let mut selection = if state.modifiers.shift() {
+                            self.selected_elements.clone()
+                        } else {
+                            BTreeSet::new()
+                        };
+                        selection.extend(
+                            self.layout
+                                .elements
+                                .iter()
+                                .enumerate()
+                                .filter(|(_, element)| element.bounding_rect().intersects(&band))
+                                .map(|(index, _)| index),
+                        );
+                        debug!(?selection, "selecting elements");
+                        shell.publish(Message::UpdateSelection(selection));
                     }
                 }
-
-                if let Some(i) = state.held_element {
-                    debug!(index = i, "deselecting element");
-                    shell.publish(Message::ClearCache(i));
-                }
-                state.held_element = None;
-
-                state.interaction = Interaction::None;
             }
             _ => {}
         }
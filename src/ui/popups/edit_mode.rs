@@ -1,19 +1,35 @@
-use crate::{message::*, nuhxboard::*, types::*, ui::components::*};
+use crate::{
+    message::*,
+    nuhxboard::*,
+    types::*,
+    ui::{app::Main, components::*},
+};
+use geo::Coord;
 use iced::{
     never,
     widget::{button, checkbox, column, pick_list, rich_text, row, span, text, text_input},
-    window, Alignment, Font, Padding, Theme,
+    window, Alignment, Font, Padding, Task, Theme,
 };
 use iced_aw::{helpers::selection_list_with, number_input, selection_list};
-use iced_multi_window::Window;
+use iced_multi_window::{State, Window};
 use nuhxboard_types::{
-    layout::{BoardElement, CommonDefinitionRef, OrderedFloat, SerializablePoint},
+    labels::Locale,
+    layout::{BoardElement, CommonDefinitionRef, KeyShape, OrderedFloat, SerializablePoint},
     style::{self, FontStyle},
 };
+use std::any::Any;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KeyboardProperties;
 impl Window<NuhxBoard, Theme, Message> for KeyboardProperties {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
     fn settings(&self) -> window::Settings {
         window::Settings {
             size: iced::Size {
@@ -25,7 +41,7 @@ impl Window<NuhxBoard, Theme, Message> for KeyboardProperties {
         }
     }
 
-    fn view<'a>(&self, app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(&self, app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
         column![
             row![
                 text("Width: "),
@@ -53,6 +69,14 @@ impl Window<NuhxBoard, Theme, Message> for KeyboardProperties {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SaveDefinitionAs;
 impl Window<NuhxBoard, Theme, Message> for SaveDefinitionAs {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
     fn theme(&self, _app: &NuhxBoard) -> Theme {
         Theme::Light
     }
@@ -61,7 +85,7 @@ impl Window<NuhxBoard, Theme, Message> for SaveDefinitionAs {
         "Save Definition As".to_string()
     }
 
-    fn view<'a>(&self, app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(&self, app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
         column![
             row![
                 text("Category: "),
@@ -74,12 +98,19 @@ impl Window<NuhxBoard, Theme, Message> for SaveDefinitionAs {
                 text_input("", &app.save_layout_as_name,)
                     .on_input(|v| Message::ChangeTextInput(TextInputType::SaveKeyboardAsName, v))
             ],
-            button("Save").on_press(Message::SaveLayout(Some(
-                KEYBOARDS_PATH
+            checkbox("NohBoard compatible", app.save_layout_as_nohboard)
+                .on_toggle(|_| Message::ToggleSaveLayoutAsNohBoard),
+            button("Save").on_press({
+                let path = KEYBOARDS_PATH
                     .join(&app.save_keyboard_as_category)
                     .join(&app.save_layout_as_name)
-                    .join("keyboard.json")
-            ))),
+                    .join("keyboard.json");
+                if app.save_layout_as_nohboard {
+                    Message::ExportLayout(path)
+                } else {
+                    Message::SaveLayout(Some(path))
+                }
+            }),
         ]
         .into()
     }
@@ -88,7 +119,7 @@ impl Window<NuhxBoard, Theme, Message> for SaveDefinitionAs {
         window::Settings {
             size: iced::Size {
                 width: 400.0,
-                height: 100.0,
+                height: 130.0,
             },
             resizable: false,
             ..Default::default()
@@ -99,6 +130,14 @@ impl Window<NuhxBoard, Theme, Message> for SaveDefinitionAs {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SaveStyleAs;
 impl Window<NuhxBoard, Theme, Message> for SaveStyleAs {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
     fn settings(&self) -> window::Settings {
         window::Settings {
             size: iced::Size {
@@ -110,7 +149,7 @@ impl Window<NuhxBoard, Theme, Message> for SaveStyleAs {
         }
     }
 
-    fn view<'a>(&self, app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(&self, app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
         column![
             row![
                 text("Name: "),
@@ -145,6 +184,14 @@ impl Window<NuhxBoard, Theme, Message> for SaveStyleAs {
 pub struct KeyboardStyle;
 
 impl Window<NuhxBoard, Theme, Message> for KeyboardStyle {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
     fn settings(&self) -> window::Settings {
         window::Settings {
             size: iced::Size {
@@ -156,7 +203,7 @@ impl Window<NuhxBoard, Theme, Message> for KeyboardStyle {
         }
     }
 
-    fn view<'a>(&self, app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(&self, app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
         let keyboard = column![
             category_label("Keyboard"),
             text("Background"),
@@ -375,6 +422,14 @@ pub struct ElementProperties {
 }
 
 impl Window<NuhxBoard, Theme, Message> for ElementProperties {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
     fn settings(&self) -> window::Settings {
         window::Settings {
             resizable: false,
@@ -386,7 +441,7 @@ impl Window<NuhxBoard, Theme, Message> for ElementProperties {
         }
     }
 
-    fn view<'a>(&'a self, app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(&'a self, app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
         let element = &app.layout.elements[self.index];
         let index = self.index;
         match element {
@@ -417,6 +472,23 @@ impl Window<NuhxBoard, Theme, Message> for ElementProperties {
                         button("Center").on_press(Message::CenterTextPosition(index)),
                     ]
                     .align_y(Alignment::Center),
+                    row![
+                        text("Corner Radius: "),
+                        number_input(&def.corner_radius, 0.0.., move |v| {
+                            Message::ChangeElement(index, ElementProperty::CornerRadius(v))
+                        }),
+                        checkbox("Ellipse", def.shape == KeyShape::Ellipse).on_toggle(move |v| {
+                            Message::ChangeElement(
+                                index,
+                                ElementProperty::Shape(if v {
+                                    KeyShape::Ellipse
+                                } else {
+                                    KeyShape::Polygon
+                                }),
+                            )
+                        }),
+                    ]
+                    .align_y(Alignment::Center),
                     row![
                         text("Boundaries: "),
                         number_input(
@@ -514,15 +586,9 @@ impl Window<NuhxBoard, Theme, Message> for ElementProperties {
                                     .filter(|v| **v != def.boundaries.len() - 1)
                                     .map(move |v| Message::SwapBoundaries(index, *v, v + 1))
                             ),
-                            button("Rectangle").on_press_maybe(
-                                if app.windows.any_of(&RectangleDialog { index: self.index }) {
-                                    None
-                                } else {
-                                    Some(Message::Open(Box::new(RectangleDialog {
-                                        index: self.index,
-                                    })))
-                                }
-                            ),
+                            button("Rectangle").on_press(Message::Open(Box::new(
+                                RectangleDialog { index: self.index }
+                            ))),
                         ],
                         selection_list_with(
                             &def.boundaries,
@@ -652,6 +718,23 @@ impl Window<NuhxBoard, Theme, Message> for ElementProperties {
                         button("Center").on_press(Message::CenterTextPosition(index)),
                     ]
                     .align_y(Alignment::Center),
+                    row![
+                        text("Corner Radius: "),
+                        number_input(&def.corner_radius, 0.0.., move |v| {
+                            Message::ChangeElement(index, ElementProperty::CornerRadius(v))
+                        }),
+                        checkbox("Ellipse", def.shape == KeyShape::Ellipse).on_toggle(move |v| {
+                            Message::ChangeElement(
+                                index,
+                                ElementProperty::Shape(if v {
+                                    KeyShape::Ellipse
+                                } else {
+                                    KeyShape::Polygon
+                                }),
+                            )
+                        }),
+                    ]
+                    .align_y(Alignment::Center),
                     row![
                         column![
                             button("Add").on_press(Message::ChangeElement(
@@ -724,15 +807,9 @@ impl Window<NuhxBoard, Theme, Message> for ElementProperties {
                                     .filter(|v| **v != def.boundaries.len() - 1)
                                     .map(move |v| Message::SwapBoundaries(index, *v, v + 1))
                             ),
-                            button("Rectangle").on_press_maybe(
-                                if app.windows.any_of(&RectangleDialog { index: self.index }) {
-                                    None
-                                } else {
-                                    Some(Message::Open(Box::new(RectangleDialog {
-                                        index: self.index,
-                                    })))
-                                }
-                            ),
+                            button("Rectangle").on_press(Message::Open(Box::new(
+                                RectangleDialog { index: self.index }
+                            ))),
                         ],
                         selection_list_with(
                             def.boundaries,
@@ -802,7 +879,21 @@ pub struct RectangleDialog {
     pub index: usize,
 }
 
+#[derive(Default)]
+struct RectangleDialogState {
+    position: Coord<f32>,
+    size: Coord<f32>,
+}
+
 impl Window<NuhxBoard, Theme, Message> for RectangleDialog {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(ElementProperties { index: self.index }))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
     fn settings(&self) -> window::Settings {
         window::Settings {
             resizable: false,
@@ -814,64 +905,60 @@ impl Window<NuhxBoard, Theme, Message> for RectangleDialog {
         }
     }
 
-    fn view<'a>(&'a self, app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
-        let index = self.index;
+    fn state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(RectangleDialogState::default()))
+    }
+
+    fn update(&self, state: &mut State, message: Message) -> Task<Message> {
+        let Message::WindowUpdate(_, message) = message else {
+            return Task::none();
+        };
+        let state = state.get_mut::<RectangleDialogState>();
+        match message {
+            WindowMessage::RectanglePositionX(v) => state.position.x = v,
+            WindowMessage::RectanglePositionY(v) => state.position.y = v,
+            WindowMessage::RectangleSizeX(v) => state.size.x = v,
+            WindowMessage::RectangleSizeY(v) => state.size.y = v,
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn view<'a>(
+        &'a self,
+        _app: &'a NuhxBoard,
+        state: &'a State,
+    ) -> iced::Element<'a, Message, Theme> {
+        let id = state.id();
+        let rectangle = state.get::<RectangleDialogState>();
         column![
             row![
                 text("Position: "),
-                number_input(
-                    &app.number_input
-                        .rectangle_position_x
-                        .get(&self.index)
-                        .copied()
-                        .unwrap_or_default(),
-                    0.0..,
-                    move |v| {
-                        Message::ChangeNumberInput(NumberInputType::RectanglePositionX(index, v))
-                    }
-                ),
-                number_input(
-                    &app.number_input
-                        .rectangle_position_y
-                        .get(&self.index)
-                        .copied()
-                        .unwrap_or_default(),
-                    0.0..,
-                    move |v| {
-                        Message::ChangeNumberInput(NumberInputType::RectanglePositionY(index, v))
-                    }
-                ),
+                number_input(&rectangle.position.x, 0.0.., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::RectanglePositionX(v))
+                }),
+                number_input(&rectangle.position.y, 0.0.., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::RectanglePositionY(v))
+                }),
             ]
             .align_y(Alignment::Center),
             row![
                 text("Size: "),
-                number_input(
-                    &app.number_input
-                        .rectangle_size_x
-                        .get(&self.index)
-                        .copied()
-                        .unwrap_or_default(),
-                    0.0..,
-                    move |v| {
-                        Message::ChangeNumberInput(NumberInputType::RectangleSizeX(index, v))
-                    }
-                ),
-                number_input(
-                    &app.number_input
-                        .rectangle_size_y
-                        .get(&self.index)
-                        .copied()
-                        .unwrap_or_default(),
-                    0.0..,
-                    move |v| {
-                        Message::ChangeNumberInput(NumberInputType::RectangleSizeY(index, v))
-                    }
-                ),
+                number_input(&rectangle.size.x, 0.0.., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::RectangleSizeX(v))
+                }),
+                number_input(&rectangle.size.y, 0.0.., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::RectangleSizeY(v))
+                }),
             ]
             .align_y(Alignment::Center),
             row![
-                button("Cancel").on_press(Message::CloseAllOf(Box::new(self.clone()))),
-                button("Apply").on_press(Message::MakeRectangle(self.index))
+                button("Cancel").on_press(Message::WindowUpdate(id, WindowMessage::Close)),
+                button("Apply").on_press(Message::MakeRectangle {
+                    index: self.index,
+                    position: rectangle.position,
+                    size: rectangle.size,
+                })
             ]
         ]
         .into()
@@ -892,6 +979,14 @@ pub struct ElementStyle {
 }
 
 impl Window<NuhxBoard, Theme, Message> for ElementStyle {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
     fn settings(&self) -> window::Settings {
         window::Settings {
             resizable: false,
@@ -933,7 +1028,7 @@ impl Window<NuhxBoard, Theme, Message> for ElementStyle {
         Theme::Light
     }
 
-    fn view<'a>(&'a self, app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(&'a self, app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
         let id = self.id;
         let default = match app
             .layout
@@ -1266,3 +1361,283 @@ impl Window<NuhxBoard, Theme, Message> for ElementStyle {
         }
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransformDialog;
+
+struct TransformDialogState {
+    angle: f32,
+    scale: f32,
+    custom_pivot: bool,
+    pivot: Coord<f32>,
+}
+
+impl Default for TransformDialogState {
+    fn default() -> Self {
+        Self {
+            angle: 0.0,
+            scale: 1.0,
+            custom_pivot: false,
+            pivot: Coord::zero(),
+        }
+    }
+}
+
+impl Window<NuhxBoard, Theme, Message> for TransformDialog {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
+    fn settings(&self) -> window::Settings {
+        window::Settings {
+            resizable: false,
+            size: iced::Size {
+                width: 400.0,
+                height: 200.0,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(TransformDialogState::default()))
+    }
+
+    fn update(&self, state: &mut State, message: Message) -> Task<Message> {
+        let Message::WindowUpdate(_, message) = message else {
+            return Task::none();
+        };
+        let state = state.get_mut::<TransformDialogState>();
+        match message {
+            WindowMessage::TransformAngle(v) => state.angle = v,
+            WindowMessage::TransformScale(v) => state.scale = v,
+            WindowMessage::TransformCustomPivot(v) => state.custom_pivot = v,
+            WindowMessage::TransformPivotX(v) => state.pivot.x = v,
+            WindowMessage::TransformPivotY(v) => state.pivot.y = v,
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn view<'a>(
+        &'a self,
+        app: &'a NuhxBoard,
+        state: &'a State,
+    ) -> iced::Element<'a, Message, Theme> {
+        let id = state.id();
+        let transform = state.get::<TransformDialogState>();
+        let pivot = transform.custom_pivot.then_some(transform.pivot);
+        let enabled = app.edit_mode && !app.target_elements().is_empty();
+        let apply =
+            move |transform| enabled.then_some(Message::TransformElements { transform, pivot });
+        column![
+            row![
+                text("Angle (clockwise): "),
+                number_input(&transform.angle, -360.0..=360.0, move |v| {
+                    Message::WindowUpdate(id, WindowMessage::TransformAngle(v))
+                }),
+                button("Rotate").on_press_maybe(apply(ElementTransform::Rotate(transform.angle))),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("Factor: "),
+                number_input(&transform.scale, 0.01.., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::TransformScale(v))
+                }),
+                button("Scale").on_press_maybe(apply(ElementTransform::Scale(transform.scale))),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                button("Flip Horizontally")
+                    .on_press_maybe(apply(ElementTransform::FlipHorizontally)),
+                button("Flip Vertically").on_press_maybe(apply(ElementTransform::FlipVertically)),
+            ],
+            checkbox("Custom pivot", transform.custom_pivot).on_toggle(move |v| {
+                Message::WindowUpdate(id, WindowMessage::TransformCustomPivot(v))
+            }),
+            row![
+                text("Pivot: "),
+                number_input(&transform.pivot.x, .., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::TransformPivotX(v))
+                }),
+                number_input(&transform.pivot.y, .., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::TransformPivotY(v))
+                }),
+            ]
+            .align_y(Alignment::Center),
+        ]
+        .into()
+    }
+
+    fn title(&self, _app: &NuhxBoard) -> String {
+        "Transform".to_string()
+    }
+
+    fn theme(&self, _app: &NuhxBoard) -> Theme {
+        Theme::Light
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelKeys;
+
+impl Window<NuhxBoard, Theme, Message> for LabelKeys {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
+    fn settings(&self) -> window::Settings {
+        window::Settings {
+            resizable: false,
+            size: iced::Size {
+                width: 300.0,
+                height: 130.0,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(Locale::default()))
+    }
+
+    fn update(&self, state: &mut State, message: Message) -> Task<Message> {
+        if let Message::WindowUpdate(_, WindowMessage::LabelLocale(locale)) = message {
+            *state.get_mut::<Locale>() = locale;
+        }
+        Task::none()
+    }
+
+    fn view<'a>(
+        &'a self,
+        app: &'a NuhxBoard,
+        state: &'a State,
+    ) -> iced::Element<'a, Message, Theme> {
+        let id = state.id();
+        let locale = *state.get::<Locale>();
+        let target = if app.selected_elements.is_empty() {
+            "all keys"
+        } else {
+            "selected keys"
+        };
+        column![
+            row![
+                text("Language: "),
+                pick_list(Locale::ALL, Some(locale), move |v| {
+                    Message::WindowUpdate(id, WindowMessage::LabelLocale(v))
+                }),
+            ]
+            .align_y(Alignment::Center),
+            text(format!("Applies to {target}")),
+            row![
+                button("Label Keys")
+                    .on_press_maybe(app.edit_mode.then_some(Message::LabelKeys(locale))),
+                button("Relabel from US").on_press_maybe(
+                    (app.edit_mode && locale != Locale::Us).then_some(Message::RelabelKeys {
+                        from: Locale::Us,
+                        to: locale,
+                    })
+                ),
+            ]
+            .spacing(5),
+        ]
+        .spacing(5)
+        .padding(10)
+        .into()
+    }
+
+    fn title(&self, _app: &NuhxBoard) -> String {
+        "Label Keys".to_string()
+    }
+
+    fn theme(&self, _app: &NuhxBoard) -> Theme {
+        Theme::Light
+    }
+}
+
+/// Shortcuts handled in edit mode, as listed in the [`KeyboardShortcuts`] window.
+const SHORTCUTS: &[(&str, &str)] = &[
+    ("Ctrl+Z", "Undo"),
+    ("Ctrl+Shift+Z", "Redo"),
+    ("Ctrl+C", "Copy"),
+    ("Ctrl+X", "Cut"),
+    ("Ctrl+V", "Paste at the cursor"),
+    ("Ctrl+D", "Duplicate"),
+    ("Ctrl+S", "Save definition and style"),
+    ("Delete", "Remove"),
+    ("Arrow keys", "Move by 1px"),
+    ("Shift+Arrow keys", "Move by 10px"),
+    ("Tab", "Select the next element"),
+    ("Shift+Tab", "Select the previous element"),
+    ("Escape", "Clear the selection"),
+    ("Shift+Click", "Add to or remove from the selection"),
+    ("Alt+Drag", "Move without snapping"),
+    ("Double-click edge", "Insert a vertex"),
+    ("Delete on vertex", "Remove the vertex"),
+    ("Ctrl+Scroll", "Zoom"),
+    ("Ctrl+0", "Reset zoom"),
+    ("Middle-drag", "Pan"),
+    ("Space+Drag", "Pan"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardShortcuts;
+impl Window<NuhxBoard, Theme, Message> for KeyboardShortcuts {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
+    fn settings(&self) -> window::Settings {
+        window::Settings {
+            size: iced::Size {
+                width: 350.0,
+                height: 400.0,
+            },
+            resizable: false,
+            ..Default::default()
+        }
+    }
+
+    fn view<'a>(&self, _app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
+        let command = if std::env::consts::OS == "macos" {
+            "Cmd"
+        } else {
+            "Ctrl"
+        };
+        column(SHORTCUTS.iter().map(|(keys, action)| {
+            row![
+                text(keys.replace("Ctrl", command))
+                    .font(Font {
+                        weight: iced::font::Weight::Bold,
+                        ..Font::default()
+                    })
+                    .width(140),
+                text(*action),
+            ]
+            .into()
+        }))
+        .spacing(5)
+        .padding(10)
+        .into()
+    }
+
+    fn title(&self, _app: &NuhxBoard) -> String {
+        "Keyboard Shortcuts".to_string()
+    }
+
+    fn theme(&self, _app: &NuhxBoard) -> Theme {
+        Theme::Light
+    }
+}
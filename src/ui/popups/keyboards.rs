@@ -0,0 +1,510 @@
+use crate::{archive::PackConflicts, message::*, nuhxboard::*, types::*, ui::app::LoadKeyboard};
+use iced::{
+    widget::{button, checkbox, column, pick_list, row, text, text_input},
+    window, Alignment, Task, Theme, Vector,
+};
+use iced_aw::number_input;
+use iced_multi_window::{State, Window};
+use nuhxboard_types::generator::{self, FormFactor, LayoutOptions, Standard};
+use std::{any::Any, path::PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewKeyboard;
+
+struct NewKeyboardState {
+    category: String,
+    name: String,
+    options: LayoutOptions,
+    size: iced::Size,
+}
+
+impl NewKeyboardState {
+    /// Fits the size to the layout generated from the current options, if they're valid.
+    fn fit(&mut self) {
+        if let Ok(layout) = generator::generate(&self.options) {
+            self.size = iced::Size {
+                width: layout.width,
+                height: layout.height,
+            };
+        }
+    }
+}
+
+impl Default for NewKeyboardState {
+    fn default() -> Self {
+        let mut state = Self {
+            category: String::new(),
+            name: String::new(),
+            options: LayoutOptions::default(),
+            size: iced::Size::ZERO,
+        };
+        state.fit();
+        state
+    }
+}
+
+impl Window<NuhxBoard, Theme, Message> for NewKeyboard {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(LoadKeyboard))
+    }
+
+    fn offset(&self) -> Option<Vector> {
+        Some(Vector::new(20.0, 20.0))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
+    fn settings(&self) -> window::Settings {
+        window::Settings {
+            resizable: false,
+            size: iced::Size {
+                width: 450.0,
+                height: 240.0,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(NewKeyboardState::default()))
+    }
+
+    fn update(&self, state: &mut State, message: Message) -> Task<Message> {
+        let Message::WindowUpdate(_, message) = message else {
+            return Task::none();
+        };
+        let state = state.get_mut::<NewKeyboardState>();
+        match message {
+            WindowMessage::NewKeyboardCategory(v) => state.category = v,
+            WindowMessage::NewKeyboardName(v) => state.name = v,
+            WindowMessage::NewKeyboardWidth(v) => state.size.width = v,
+            WindowMessage::NewKeyboardHeight(v) => state.size.height = v,
+            WindowMessage::NewKeyboardFormFactor(v) => {
+                state.options.form_factor = v;
+                state.fit();
+            }
+            WindowMessage::NewKeyboardStandard(v) => {
+                state.options.standard = v;
+                state.fit();
+            }
+            WindowMessage::NewKeyboardUnit(v) => {
+                state.options.unit = v;
+                state.fit();
+            }
+            WindowMessage::NewKeyboardGap(v) => {
+                state.options.gap = v;
+                state.fit();
+            }
+            WindowMessage::NewKeyboardMouse(v) => {
+                state.options.mouse = v;
+                state.fit();
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn view<'a>(
+        &'a self,
+        app: &'a NuhxBoard,
+        state: &'a State,
+    ) -> iced::Element<'a, Message, Theme> {
+        let id = state.id();
+        let new = state.get::<NewKeyboardState>();
+        let existing = app
+            .keyboard_category_options
+            .iter()
+            .find(|category| **category == new.category)
+            .cloned();
+        let valid = KeyboardItem::is_valid_name(&new.category)
+            && KeyboardItem::is_valid_name(&new.name)
+            && new.options.check().is_ok();
+        column![
+            row![
+                text("Category: "),
+                text_input("New category", &new.category).on_input(move |v| {
+                    Message::WindowUpdate(id, WindowMessage::NewKeyboardCategory(v))
+                }),
+                pick_list(
+                    app.keyboard_category_options.as_slice(),
+                    existing,
+                    move |v| { Message::WindowUpdate(id, WindowMessage::NewKeyboardCategory(v)) }
+                )
+                .placeholder("Existing"),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("Name: "),
+                text_input("", &new.name).on_input(move |v| {
+                    Message::WindowUpdate(id, WindowMessage::NewKeyboardName(v))
+                }),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("Keys: "),
+                pick_list(FormFactor::ALL, Some(new.options.form_factor), move |v| {
+                    Message::WindowUpdate(id, WindowMessage::NewKeyboardFormFactor(v))
+                }),
+                pick_list(Standard::ALL, Some(new.options.standard), move |v| {
+                    Message::WindowUpdate(id, WindowMessage::NewKeyboardStandard(v))
+                }),
+                checkbox("Mouse", new.options.mouse).on_toggle(move |v| {
+                    Message::WindowUpdate(id, WindowMessage::NewKeyboardMouse(v))
+                }),
+            ]
+            .spacing(5)
+            .align_y(Alignment::Center),
+            row![
+                text("Key size: "),
+                number_input(&new.options.unit, 1.0.., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::NewKeyboardUnit(v))
+                }),
+                text("Gap: "),
+                number_input(&new.options.gap, 0.0.., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::NewKeyboardGap(v))
+                }),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("Width: "),
+                number_input(&new.size.width, 1.0.., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::NewKeyboardWidth(v))
+                }),
+                text("Height: "),
+                number_input(&new.size.height, 1.0.., move |v| {
+                    Message::WindowUpdate(id, WindowMessage::NewKeyboardHeight(v))
+                }),
+            ]
+            .align_y(Alignment::Center),
+            button("Create").on_press_maybe(valid.then(|| Message::CreateKeyboard {
+                category: new.category.clone(),
+                name: new.name.clone(),
+                options: new.options,
+                size: new.size,
+            })),
+        ]
+        .spacing(5)
+        .padding(10)
+        .into()
+    }
+
+    fn title(&self, _app: &NuhxBoard) -> String {
+        "New Keyboard".to_string()
+    }
+
+    fn theme(&self, _app: &NuhxBoard) -> Theme {
+        Theme::Light
+    }
+}
+
+/// Asks for the name to rename `item` to, or to give its copy if `duplicate` is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameItem {
+    pub item: KeyboardItem,
+    pub duplicate: bool,
+}
+
+impl Window<NuhxBoard, Theme, Message> for NameItem {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(LoadKeyboard))
+    }
+
+    fn offset(&self) -> Option<Vector> {
+        Some(Vector::new(20.0, 20.0))
+    }
+
+    fn settings(&self) -> window::Settings {
+        window::Settings {
+            resizable: false,
+            size: iced::Size {
+                width: 300.0,
+                height: 80.0,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn state(&self) -> Option<Box<dyn Any>> {
+        let name = self.item.name().to_owned();
+        Some(Box::new(if self.duplicate {
+            format!("{name} (copy)")
+        } else {
+            name
+        }))
+    }
+
+    fn update(&self, state: &mut State, message: Message) -> Task<Message> {
+        if let Message::WindowUpdate(_, WindowMessage::ItemName(name)) = message {
+            *state.get_mut::<String>() = name;
+        }
+        Task::none()
+    }
+
+    fn view<'a>(
+        &'a self,
+        _app: &'a NuhxBoard,
+        state: &'a State,
+    ) -> iced::Element<'a, Message, Theme> {
+        let id = state.id();
+        let name = state.get::<String>();
+        let valid = KeyboardItem::is_valid_name(name) && name != self.item.name();
+        let message = || {
+            let item = self.item.clone();
+            let name = name.clone();
+            if self.duplicate {
+                Message::DuplicateItem { item, name }
+            } else {
+                Message::RenameItem { item, name }
+            }
+        };
+        column![
+            text_input("", name)
+                .on_input(move |v| Message::WindowUpdate(id, WindowMessage::ItemName(v))),
+            button(if self.duplicate {
+                "Duplicate"
+            } else {
+                "Rename"
+            })
+            .on_press_maybe(valid.then(message)),
+        ]
+        .spacing(5)
+        .padding(10)
+        .into()
+    }
+
+    fn title(&self, _app: &NuhxBoard) -> String {
+        if self.duplicate {
+            format!("Duplicate {}", self.item)
+        } else {
+            format!("Rename {}", self.item)
+        }
+    }
+
+    fn theme(&self, _app: &NuhxBoard) -> Theme {
+        Theme::Light
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmDelete(pub KeyboardItem);
+
+impl Window<NuhxBoard, Theme, Message> for ConfirmDelete {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(LoadKeyboard))
+    }
+
+    fn offset(&self) -> Option<Vector> {
+        Some(Vector::new(20.0, 20.0))
+    }
+
+    fn modal(&self) -> bool {
+        true
+    }
+
+    fn settings(&self) -> window::Settings {
+        window::Settings {
+            size: iced::Size {
+                width: 500.0,
+                height: 100.0,
+            },
+            resizable: false,
+            ..window::Settings::default()
+        }
+    }
+
+    fn theme(&self, _app: &NuhxBoard) -> Theme {
+        Theme::Light
+    }
+
+    fn title(&self, _app: &NuhxBoard) -> String {
+        "Delete".to_string()
+    }
+
+    fn view<'a>(&'a self, _app: &'a NuhxBoard, state: &State) -> iced::Element<'a, Message, Theme> {
+        let contents = match self.0 {
+            KeyboardItem::Category(_) => " and all of its keyboards",
+            KeyboardItem::Keyboard { .. } => " and all of its styles",
+            KeyboardItem::Style { .. } => "",
+        };
+        row![column![
+            text(format!(
+                "Are you sure you want to delete the {}{contents}? This can't be undone.",
+                self.0
+            )),
+            row![
+                button("Yes").on_press(Message::DeleteItem(self.0.clone())),
+                button("Cancel").on_press(Message::WindowUpdate(state.id(), WindowMessage::Close))
+            ]
+        ]
+        .align_x(iced::Alignment::Center)
+        .width(iced::Length::Fill)]
+        .align_y(iced::Alignment::Center)
+        .height(iced::Length::Fill)
+        .into()
+    }
+}
+
+/// Asks where to write a keyboard pack of a category or keyboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportPack(pub KeyboardItem);
+
+impl Window<NuhxBoard, Theme, Message> for ExportPack {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(LoadKeyboard))
+    }
+
+    fn offset(&self) -> Option<Vector> {
+        Some(Vector::new(20.0, 20.0))
+    }
+
+    fn settings(&self) -> window::Settings {
+        window::Settings {
+            resizable: false,
+            size: iced::Size {
+                width: 400.0,
+                height: 80.0,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn state(&self) -> Option<Box<dyn Any>> {
+        let file_name = format!("{}.zip", self.0.name());
+        let path = home::home_dir()
+            .map_or_else(|| PathBuf::from(&file_name), |home| home.join(&file_name));
+        Some(Box::new(path.display().to_string()))
+    }
+
+    fn update(&self, state: &mut State, message: Message) -> Task<Message> {
+        if let Message::WindowUpdate(_, WindowMessage::PackPath(path)) = message {
+            *state.get_mut::<String>() = path;
+        }
+        Task::none()
+    }
+
+    fn view<'a>(
+        &'a self,
+        _app: &'a NuhxBoard,
+        state: &'a State,
+    ) -> iced::Element<'a, Message, Theme> {
+        let id = state.id();
+        let path = state.get::<String>();
+        column![
+            row![
+                text("File: "),
+                text_input("", path)
+                    .on_input(move |v| Message::WindowUpdate(id, WindowMessage::PackPath(v))),
+            ]
+            .align_y(Alignment::Center),
+            button("Export").on_press_maybe((!path.is_empty()).then(|| Message::ExportPack {
+                item: self.0.clone(),
+                path: PathBuf::from(path),
+            })),
+        ]
+        .spacing(5)
+        .padding(10)
+        .into()
+    }
+
+    fn title(&self, _app: &NuhxBoard) -> String {
+        format!("Export {}", self.0)
+    }
+
+    fn theme(&self, _app: &NuhxBoard) -> Theme {
+        Theme::Light
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportPack;
+
+#[derive(Default)]
+struct ImportPackState {
+    path: String,
+    conflicts: PackConflicts,
+}
+
+impl Window<NuhxBoard, Theme, Message> for ImportPack {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(LoadKeyboard))
+    }
+
+    fn offset(&self) -> Option<Vector> {
+        Some(Vector::new(20.0, 20.0))
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
+    fn settings(&self) -> window::Settings {
+        window::Settings {
+            resizable: false,
+            size: iced::Size {
+                width: 400.0,
+                height: 120.0,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(ImportPackState::default()))
+    }
+
+    fn update(&self, state: &mut State, message: Message) -> Task<Message> {
+        let Message::WindowUpdate(_, message) = message else {
+            return Task::none();
+        };
+        let state = state.get_mut::<ImportPackState>();
+        match message {
+            WindowMessage::PackPath(v) => state.path = v,
+            WindowMessage::PackConflicts(v) => state.conflicts = v,
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn view<'a>(
+        &'a self,
+        _app: &'a NuhxBoard,
+        state: &'a State,
+    ) -> iced::Element<'a, Message, Theme> {
+        let id = state.id();
+        let import = state.get::<ImportPackState>();
+        column![
+            row![
+                text("File: "),
+                text_input("Keyboard pack (.zip)", &import.path)
+                    .on_input(move |v| Message::WindowUpdate(id, WindowMessage::PackPath(v))),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("If something already exists: "),
+                pick_list(PackConflicts::ALL, Some(import.conflicts), move |v| {
+                    Message::WindowUpdate(id, WindowMessage::PackConflicts(v))
+                }),
+            ]
+            .align_y(Alignment::Center),
+            button("Import").on_press_maybe((!import.path.is_empty()).then(|| {
+                Message::ImportPack {
+                    path: PathBuf::from(&import.path),
+                    conflicts: import.conflicts,
+                }
+            })),
+        ]
+        .spacing(5)
+        .padding(10)
+        .into()
+    }
+
+    fn title(&self, _app: &NuhxBoard) -> String {
+        "Import Keyboard Pack".to_string()
+    }
+
+    fn theme(&self, _app: &NuhxBoard) -> Theme {
+        Theme::Light
+    }
+}
@@ -1,15 +1,17 @@
 pub mod edit_mode;
+pub mod keyboards;
 
 pub use edit_mode::*;
+pub use keyboards::*;
 
 use std::error::Error;
 
-use crate::{message::*, nuhxboard::*, types::*};
+use crate::{message::*, nuhxboard::*, types::*, ui::app::Main};
 use iced::{
     widget::{button, column, container, row, text},
-    window, Theme,
+    window, Theme, Vector,
 };
-use iced_multi_window::Window;
+use iced_multi_window::{State, Window};
 
 #[derive(Debug, Clone)]
 pub struct ErrorPopup {
@@ -17,12 +19,20 @@ pub struct ErrorPopup {
     // having some app state dedicated to tracking errors corresponding to window IDs, each
     // instance of an error popup knows its own error. This is put to much greater use for
     // individual key styles.
-    // It is important to note, however, that these properties are unable to be changed. I would
-    // argue that mutable per-window state would be problematically complex, but it is nevertheless
-    // a limitation on the versatility of my multi-window system.
+    // It is important to note, however, that these properties are unable to be changed, as they
+    // make up the window's identity. Anything that needs to change over the window's lifetime
+    // belongs in its `State` instead (see `RectangleDialog`).
     pub error: NuhxBoardError,
 }
 impl Window<NuhxBoard, Theme, Message> for ErrorPopup {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn offset(&self) -> Option<Vector> {
+        Some(Vector::new(20.0, 20.0))
+    }
+
     fn settings(&self) -> window::Settings {
         window::Settings {
             size: iced::Size {
@@ -42,7 +52,7 @@ impl Window<NuhxBoard, Theme, Message> for ErrorPopup {
         "Error".to_string()
     }
 
-    fn view<'a>(&self, _app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(&self, _app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
         let error = &self.error;
         let kind = error.to_string();
         let info = error.source().map(|e| e.to_string()).unwrap_or_default();
@@ -84,6 +94,22 @@ impl Action {
 }
 
 impl Window<NuhxBoard, Theme, Message> for UnsavedChangesPopup {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn offset(&self) -> Option<Vector> {
+        Some(Vector::new(20.0, 20.0))
+    }
+
+    fn modal(&self) -> bool {
+        true
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
     fn settings(&self) -> window::Settings {
         window::Settings {
             size: iced::Size {
@@ -103,7 +129,11 @@ impl Window<NuhxBoard, Theme, Message> for UnsavedChangesPopup {
         "Discard changes".to_string()
     }
 
-    fn view<'a>(&'a self, _app: &'a NuhxBoard) -> iced::Element<'a, Message, Theme> {
+    fn view<'a>(
+        &'a self,
+        _app: &'a NuhxBoard,
+        _state: &State,
+    ) -> iced::Element<'a, Message, Theme> {
         row![column![
             text(format!(
                 "You have unsaved changes. {} will undo them. Are you sure you want to {}?",
@@ -122,3 +152,67 @@ impl Window<NuhxBoard, Theme, Message> for UnsavedChangesPopup {
         .into()
     }
 }
+
+/// Offers to restore the unsaved edits left behind by a previous run.
+#[derive(Debug, Clone)]
+pub struct RecoveryPopup;
+
+impl Window<NuhxBoard, Theme, Message> for RecoveryPopup {
+    fn parent(&self) -> Option<Box<dyn Window<NuhxBoard, Theme, Message>>> {
+        Some(Box::new(Main))
+    }
+
+    fn offset(&self) -> Option<Vector> {
+        Some(Vector::new(20.0, 20.0))
+    }
+
+    fn modal(&self) -> bool {
+        true
+    }
+
+    fn singleton(&self) -> bool {
+        true
+    }
+
+    fn settings(&self) -> window::Settings {
+        window::Settings {
+            size: iced::Size {
+                width: 600.0,
+                height: 100.0,
+            },
+            resizable: false,
+            ..window::Settings::default()
+        }
+    }
+
+    fn theme(&self, _app: &NuhxBoard) -> Theme {
+        Theme::Light
+    }
+
+    fn title(&self, _app: &NuhxBoard) -> String {
+        "Restore unsaved changes".to_string()
+    }
+
+    fn view<'a>(&'a self, app: &'a NuhxBoard, _state: &State) -> iced::Element<'a, Message, Theme> {
+        let keyboard = app
+            .recovered
+            .as_ref()
+            .and_then(|snapshot| snapshot.layout_name.as_ref())
+            .map(|name| format!(" to {name}"))
+            .unwrap_or_default();
+        row![column![
+            text(format!(
+                "NuhxBoard closed with unsaved changes{keyboard}. Do you want to restore them?"
+            )),
+            row![
+                button("Restore").on_press(Message::RestoreRecovery),
+                button("Discard").on_press(Message::DiscardRecovery)
+            ]
+        ]
+        .align_x(iced::Alignment::Center)
+        .width(iced::Length::Fill)]
+        .align_y(iced::Alignment::Center)
+        .height(iced::Length::Fill)
+        .into()
+    }
+}